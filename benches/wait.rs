@@ -0,0 +1,163 @@
+//! Compares the old `find_element` polling loop against the MutationObserver wait in
+//! `src/wait.js`, waiting on the practice portal's Duo prompt for the trust button.
+//!
+//! Needs Chrome or Chromium, run with `cargo bench --bench wait`.
+
+use chromiumoxide::{Browser, BrowserConfig, Page};
+use futures::StreamExt;
+use std::error::Error;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The practice portal itself, the crate is a binary so its source is pulled in directly
+#[path = "../src/practice.rs"]
+#[allow(dead_code)]
+mod practice;
+
+/// Stand in for the one PeopleSoft type the practice portal uses
+mod school {
+    #[derive(Clone)]
+    pub struct PeopleSoftConfig {
+        pub host: String,
+        pub site: String,
+        pub portal: String,
+        pub node: String,
+    }
+
+    impl Default for PeopleSoftConfig {
+        fn default() -> Self {
+            Self {
+                host: String::new(),
+                site: String::new(),
+                portal: "EMPLOYEE".to_string(),
+                node: String::new(),
+            }
+        }
+    }
+}
+
+use practice::{DuoScenario, PracticePortal, Scenario};
+
+const WAIT_SCRIPT: &str = include_str!("../src/wait.js");
+const SELECTOR: &str = "#trust-browser-button";
+const ROUNDS: u64 = 30;
+/// Milliseconds the practice Duo prompt takes to be approved
+const DUO_DELAY: u64 = 500;
+
+fn now_ms() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+        * 1000.0
+}
+
+/// The loop `wait_element_agressive_retry` used, returns how many CDP calls it made
+async fn poll(page: &Page) -> usize {
+    let mut calls = 0;
+    loop {
+        calls += 1;
+        if page.find_element(SELECTOR).await.is_ok() {
+            return calls;
+        }
+    }
+}
+
+async fn observe(page: &Page) -> Result<usize, Box<dyn Error>> {
+    let selectors = serde_json::to_string(&[SELECTOR])?;
+    page.evaluate_expression(format!("({WAIT_SCRIPT})({selectors}, 5000)"))
+        .await?;
+    Ok(1)
+}
+
+/// Milliseconds between the button being inserted and each strategy seeing it, and CDP calls made
+async fn measure(page: &Page, url: &str, observer: bool) -> Result<(f64, usize), Box<dyn Error>> {
+    page.goto(url).await?;
+    let calls = if observer {
+        observe(page).await?
+    } else {
+        poll(page).await
+    };
+    let detected = now_ms();
+    let inserted: f64 = page
+        .evaluate_expression(
+            r#"performance.timeOrigin + performance.getEntriesByName("duo-outcome")[0].startTime"#,
+        )
+        .await?
+        .into_value()?;
+    Ok((detected - inserted, calls))
+}
+
+fn summary(name: &str, mut latencies: Vec<f64>, calls: usize) {
+    latencies.sort_by(|a, b| a.total_cmp(b));
+    let at = |quantile: f64| latencies[((latencies.len() - 1) as f64 * quantile).round() as usize];
+    println!(
+        "{name:<9} median {:>6.2}ms  p95 {:>6.2}ms  max {:>6.2}ms  {:>7.1} CDP calls per wait",
+        at(0.5),
+        at(0.95),
+        at(1.0),
+        calls as f64 / latencies.len() as f64
+    );
+}
+
+/// Logs in to the practice portal so every load of `/duo` shows a fresh push
+async fn log_in(page: &Page, host: &str, scenario: &Scenario) -> Result<(), Box<dyn Error>> {
+    page.goto(format!("{host}/login")).await?;
+    page.find_element("#userid")
+        .await?
+        .click()
+        .await?
+        .type_str(&scenario.username)
+        .await?;
+    page.find_element("#pwd")
+        .await?
+        .click()
+        .await?
+        .type_str(&scenario.password)
+        .await?;
+    page.find_element("button[type=submit]")
+        .await?
+        .click()
+        .await?;
+    page.wait_for_navigation().await?;
+    Ok(())
+}
+
+#[async_std::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let scenario = Scenario {
+        duo: DuoScenario::Approve,
+        duo_delay: DUO_DELAY,
+        latency: 0,
+        ..Scenario::default()
+    };
+    let portal = PracticePortal::start(scenario.clone())?;
+    let host = portal.config().host;
+
+    let (mut browser, mut handler) = Browser::launch(BrowserConfig::builder().build()?).await?;
+    let handle = async_std::task::spawn(async move { while handler.next().await.is_some() {} });
+    let page = browser.new_page("about:blank").await?;
+    log_in(&page, &host, &scenario).await?;
+
+    let url = format!("{host}/duo");
+    let mut results = [(Vec::new(), 0), (Vec::new(), 0)];
+    for round in 0..ROUNDS {
+        // alternate which goes first so warm up and caching favour neither
+        for observer in [round % 2 == 0, round % 2 == 1] {
+            let (latency, calls) = measure(&page, &url, observer).await?;
+            let (latencies, total) = &mut results[observer as usize];
+            latencies.push(latency);
+            *total += calls;
+        }
+    }
+    let [(polled, poll_calls), (observed, observe_calls)] = results;
+    println!(
+        "{ROUNDS} waits for the practice portal's Duo trust button, shown {DUO_DELAY}ms after load"
+    );
+    summary("polling", polled, poll_calls);
+    summary("observer", observed, observe_calls);
+
+    browser.close().await?;
+    browser.wait().await?;
+    handle.await;
+    Ok(())
+}
@@ -1,5 +1,11 @@
-use clap::Parser;
 use crate::ascii;
+use crate::block::{Resource, DEFAULT_BLOCKED_URLS};
+use crate::elements::{OutputFormat, SelectorFormat};
+use crate::schedule::RegistrationTime;
+use crate::school::{PeopleSoftConfig, School};
+use chrono_tz::Tz;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[command(name = "course-sniper")]
@@ -7,14 +13,431 @@ use crate::ascii;
 #[command(about = ascii::BANNER, long_about = None)]
 pub struct SniperArgs {
     /// Attaches to the headless browser and displays it
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub attach: bool,
 
-    /// Number of snipers that will run (not currently implemented)
-    #[arg(short, long, value_name = "NUMBER", default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..20))]
+    /// Number of pages that reload and enroll concurrently at registration time
+    #[arg(short, long, global = true, value_name = "NUMBER", default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..20))]
     pub snipers: u8,
 
-    /// Enables debug mode which screenshots the page on error
-    #[arg(short, long)]
+    /// Milliseconds between each sniper's firing time, spread evenly around registration time
+    #[arg(long, global = true, value_name = "MS", default_value_t = 100)]
+    pub stagger: u64,
+
+    /// Fires on the local clock instead of estimating the portal's clock from its responses
+    #[arg(long, global = true)]
+    pub local_clock: bool,
+
+    /// Selects, enrolls and confirms with one script in the page instead of clicking each element
+    #[arg(long, global = true)]
+    pub fast_enroll: bool,
+
+    /// Resource types dropped while enrolling, the status icons the results are read from always load
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_delimiter = ',',
+        default_values_t = [Resource::Image, Resource::Stylesheet, Resource::Font, Resource::Media],
+        help_heading = "Blocking"
+    )]
+    pub block: Vec<Resource>,
+
+    /// Url pattern dropped while enrolling, `*` matches anything, analytics scripts by default
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATTERN",
+        default_values_t = DEFAULT_BLOCKED_URLS.map(String::from),
+        help_heading = "Blocking"
+    )]
+    pub block_url: Vec<String>,
+
+    /// Loads every resource while enrolling
+    #[arg(long, global = true, help_heading = "Blocking")]
+    pub no_block: bool,
+
+    /// Seconds between reloads of a second tab that keep the session alive until registration
+    #[arg(long, global = true, value_name = "SECONDS", default_value_t = 300, value_parser = clap::value_parser!(u64).range(10..))]
+    pub keepalive_interval: u64,
+
+    /// Leaves the session alone while waiting for registration
+    #[arg(long, global = true)]
+    pub no_keepalive: bool,
+
+    /// Times a timed out Duo push is sent again before falling back to a passcode
+    #[arg(
+        long,
+        global = true,
+        value_name = "NUMBER",
+        default_value_t = 2,
+        help_heading = "Duo"
+    )]
+    pub duo_retries: u32,
+
+    /// Duo passcode entered through other options instead of waiting on a push, once pushes run
+    /// out one is asked for at the terminal
+    #[arg(
+        long,
+        global = true,
+        env = "COURSE_SNIPER_DUO_PASSCODE",
+        value_name = "CODE",
+        hide_env_values = true,
+        help_heading = "Duo"
+    )]
+    pub duo_passcode: Option<String>,
+
+    /// School whose registration portal will be used
+    #[arg(long, global = true, value_enum, default_value_t = School::Emory)]
+    pub school: School,
+
+    /// PeopleSoft host, e.g. saprod.emory.edu (overrides the school's preset)
+    #[arg(
+        long,
+        global = true,
+        required_if_eq("school", "peoplesoft"),
+        help_heading = "PeopleSoft"
+    )]
+    pub host: Option<String>,
+
+    /// PeopleSoft site name, e.g. saprod_48 (overrides the school's preset)
+    #[arg(
+        long,
+        global = true,
+        required_if_eq("school", "peoplesoft"),
+        help_heading = "PeopleSoft"
+    )]
+    pub site: Option<String>,
+
+    /// PeopleSoft node, e.g. SA (overrides the school's preset)
+    #[arg(
+        long,
+        global = true,
+        required_if_eq("school", "peoplesoft"),
+        help_heading = "PeopleSoft"
+    )]
+    pub node: Option<String>,
+
+    /// PeopleSoft portal, defaults to EMPLOYEE (overrides the school's preset)
+    #[arg(long, global = true, help_heading = "PeopleSoft")]
+    pub portal: Option<String>,
+
+    /// Timezone registration times are given in, e.g. America/New_York (defaults to the school's)
+    #[arg(long, global = true, env = "COURSE_SNIPER_TIMEZONE", value_name = "TZ")]
+    pub timezone: Option<Tz>,
+
+    /// Runs against a built in practice portal on localhost instead of the school's
+    #[arg(long, global = true, help_heading = "Practice")]
+    pub practice: bool,
+
+    /// Practice portal scenario (TOML) with its own credentials, carts, seats and open times
+    #[arg(
+        long,
+        global = true,
+        value_name = "FILE",
+        requires = "practice",
+        help_heading = "Practice"
+    )]
+    pub practice_scenario: Option<PathBuf>,
+
+    /// Chromium profile directory kept between runs, so the portal session and Duo's trusted
+    /// browser carry over instead of starting from a fresh profile each time
+    #[arg(long, global = true, value_name = "DIR")]
+    pub profile: Option<PathBuf>,
+
+    /// Session file from `session save` whose cookies are restored before the portal opens
+    #[arg(long, global = true, value_name = "FILE")]
+    pub session_file: Option<PathBuf>,
+
+    /// Reads the portal password from the first line of stdin instead of the environment, the
+    /// vault, or a prompt
+    #[arg(long, global = true)]
+    pub password_stdin: bool,
+
+    /// Credential vault kept by `creds`, defaults to course-sniper/credentials.vault in the
+    /// config directory
+    #[arg(long, global = true, env = "COURSE_SNIPER_VAULT", value_name = "FILE")]
+    pub vault: Option<PathBuf>,
+
+    /// Selector file (TOML or JSON) overriding the school's built in page elements
+    #[arg(long, global = true, value_name = "FILE")]
+    pub selectors: Option<PathBuf>,
+
+    /// Format carts, courses and results are printed in, anything but `table` moves progress
+    /// notes to stderr so stdout can be piped into scripts
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Table)]
+    pub output: OutputFormat,
+
+    /// Enables debug mode which screenshots the page on error and times both ways of reading
+    /// the cart
+    #[arg(short, long, global = true)]
     pub debug: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Shortest allowed `watch --interval`, keeps the portal from being hammered
+pub const MIN_WATCH_INTERVAL: u64 = 30;
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Prints the school's page elements in a format accepted by --selectors
+    DumpSelectors {
+        #[arg(long, value_enum, default_value_t = SelectorFormat::Toml)]
+        format: SelectorFormat,
+    },
+
+    /// Checks whether the session saved in --profile or --session-file is still logged in, without logging in
+    CheckSession,
+
+    /// Logs in and lists the shopping carts on the account
+    ListCarts {
+        #[command(flatten)]
+        login: LoginArgs,
+    },
+
+    /// Logs in and lists the courses in a shopping cart
+    ListCourses {
+        #[command(flatten)]
+        login: LoginArgs,
+
+        #[command(flatten)]
+        cart: CartArgs,
+    },
+
+    /// Validates courses in a shopping cart without prompting
+    Validate {
+        #[command(flatten)]
+        login: LoginArgs,
+
+        #[command(flatten)]
+        cart: CartArgs,
+
+        #[command(flatten)]
+        courses: CourseArgs,
+    },
+
+    /// Waits for the registration time and enrolls in courses without prompting
+    Enroll {
+        #[command(flatten)]
+        login: LoginArgs,
+
+        #[command(flatten)]
+        cart: CartArgs,
+
+        #[command(flatten)]
+        courses: CourseArgs,
+
+        /// Alternates to try in order if a class fails, e.g. "CS 170-1=CS 170-2,CS 170-3"
+        #[arg(long = "fallback", value_name = "CLASS=ALTERNATES", value_parser = parse_fallback)]
+        fallbacks: Vec<(String, Vec<String>)>,
+
+        /// Registration time in the school's timezone, e.g. 2027-01-10T08:00:00.250, or an
+        /// offset from now like +5m, enrolls immediately if left out
+        #[arg(long, env = "COURSE_SNIPER_AT", value_name = "DATETIME")]
+        at: Option<RegistrationTime>,
+    },
+
+    /// Polls a shopping cart and enrolls in courses as soon as they have open seats
+    Watch {
+        #[command(flatten)]
+        login: LoginArgs,
+
+        #[command(flatten)]
+        cart: CartArgs,
+
+        #[command(flatten)]
+        courses: CourseArgs,
+
+        /// Seconds between cart checks
+        #[arg(long, value_name = "SECONDS", default_value_t = 60, value_parser = clap::value_parser!(u64).range(MIN_WATCH_INTERVAL..))]
+        interval: u64,
+
+        /// Enrollment submissions to make before giving up
+        #[arg(long, value_name = "NUMBER", default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+        max_attempts: u32,
+
+        /// Cart checks to make before giving up, unlimited if left out
+        #[arg(long, value_name = "NUMBER", value_parser = clap::value_parser!(u32).range(1..))]
+        max_polls: Option<u32>,
+    },
+
+    /// Executes a plan file (TOML or YAML) end to end
+    Run {
+        #[arg(value_name = "PLAN")]
+        plan: PathBuf,
+    },
+
+    /// Works with plan files
+    Plan {
+        #[command(subcommand)]
+        command: PlanCommand,
+    },
+
+    /// Saves a logged in session to an encrypted file, or restores one, so headless runs can
+    /// reuse a login
+    Session {
+        #[command(subcommand)]
+        command: SessionCommand,
+    },
+
+    /// Keeps portal passwords in a local vault encrypted with COURSE_SNIPER_VAULT_KEY or a
+    /// prompted passphrase
+    Creds {
+        #[command(subcommand)]
+        command: CredsCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PlanCommand {
+    /// Logs in and checks a plan against the live shopping cart without changing anything
+    Check {
+        #[arg(value_name = "PLAN")]
+        plan: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SessionCommand {
+    /// Logs in, or waits for a login by hand in the --attach window, and saves the portal and
+    /// Duo cookies encrypted with COURSE_SNIPER_SESSION_KEY or a prompted passphrase
+    Save {
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Portal username to log in with, leave out to log in by hand
+        #[arg(long, env = "COURSE_SNIPER_USER")]
+        user: Option<String>,
+    },
+
+    /// Restores the cookies saved in a session file and checks the portal is still logged in
+    Load {
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CredsCommand {
+    /// Stores the password for a username, read from --password-stdin or prompted for
+    Add {
+        #[arg(long, env = "COURSE_SNIPER_USER")]
+        user: String,
+    },
+
+    /// Lists the usernames in the vault
+    List,
+
+    /// Forgets the password for a username
+    Remove {
+        #[arg(long, env = "COURSE_SNIPER_USER")]
+        user: String,
+    },
+}
+
+fn parse_fallback(value: &str) -> Result<(String, Vec<String>), String> {
+    let (class, alternates) = value
+        .split_once('=')
+        .ok_or("expected CLASS=ALTERNATE[,ALTERNATE...]")?;
+    let alternates: Vec<String> = alternates
+        .split(',')
+        .map(|alternate| alternate.trim().to_string())
+        .filter(|alternate| !alternate.is_empty())
+        .collect();
+    if class.trim().is_empty() || alternates.is_empty() {
+        Err("expected CLASS=ALTERNATE[,ALTERNATE...]")?
+    }
+    Ok((class.trim().to_string(), alternates))
+}
+
+impl Command {
+    /// Plan file the command runs from, if any
+    pub fn plan(&self) -> Option<&PathBuf> {
+        match self {
+            Command::Run { plan }
+            | Command::Plan {
+                command: PlanCommand::Check { plan },
+            } => Some(plan),
+            _ => None,
+        }
+    }
+
+    /// Session file the command restores, if any
+    pub fn session_file(&self) -> Option<&PathBuf> {
+        match self {
+            Command::Session {
+                command: SessionCommand::Load { file },
+            } => Some(file),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct LoginArgs {
+    /// Portal username, the password is read from --password-stdin, COURSE_SNIPER_PASSWORD, the
+    /// vault, or a prompt
+    #[arg(long, env = "COURSE_SNIPER_USER")]
+    pub user: String,
+}
+
+#[derive(Args, Debug)]
+pub struct CartArgs {
+    /// Shopping cart to open, matched against the cart name, e.g. "Spring 2027"
+    #[arg(long, env = "COURSE_SNIPER_TERM")]
+    pub term: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct CourseArgs {
+    /// Course to select by class number or part of its description, repeat for several
+    #[arg(
+        long = "class",
+        value_name = "CLASS",
+        required = true,
+        env = "COURSE_SNIPER_CLASSES",
+        value_delimiter = ','
+    )]
+    pub classes: Vec<String>,
+}
+
+impl SniperArgs {
+    /// The school's PeopleSoft preset with any of the PeopleSoft flags applied on top
+    pub fn peoplesoft_config(&self, school: School) -> PeopleSoftConfig {
+        let mut config = school.preset().unwrap_or_default();
+        if let Some(host) = &self.host {
+            config.host = host.clone();
+        }
+        if let Some(site) = &self.site {
+            config.site = site.clone();
+        }
+        if let Some(node) = &self.node {
+            config.node = node.clone();
+        }
+        if let Some(portal) = &self.portal {
+            config.portal = portal.clone();
+        }
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_fallbacks() {
+        assert_eq!(
+            parse_fallback(" 4321 = 4322, 4323 ,"),
+            Ok((
+                "4321".to_string(),
+                vec!["4322".to_string(), "4323".to_string()]
+            ))
+        );
+        for value in ["4321", "=4322", "4321=", "4321= , "] {
+            assert!(parse_fallback(value).is_err(), "{value} parsed");
+        }
+    }
 }
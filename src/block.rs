@@ -0,0 +1,135 @@
+use chromiumoxide::cdp::browser_protocol::fetch::{
+    ContinueRequestParams, DisableParams, EnableParams, EventRequestPaused, FailRequestParams,
+    RequestPattern,
+};
+use chromiumoxide::cdp::browser_protocol::network::{ErrorReason, ResourceType};
+use chromiumoxide::error::CdpError;
+use chromiumoxide::Page;
+use clap::ValueEnum;
+use futures::StreamExt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Analytics and monitoring scripts PeopleSoft portals commonly load
+pub const DEFAULT_BLOCKED_URLS: [&str; 5] = [
+    "*google-analytics.com*",
+    "*googletagmanager.com*",
+    "*doubleclick.net*",
+    "*nr-data.net*",
+    "*hotjar.com*",
+];
+
+/// Resource types that can be dropped while enrolling. Documents, scripts and XHR or fetch
+/// requests build the DOM the selectors need, so they are only blocked by url.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+    Image,
+    Stylesheet,
+    Font,
+    Media,
+}
+
+impl From<Resource> for ResourceType {
+    fn from(resource: Resource) -> Self {
+        match resource {
+            Resource::Image => ResourceType::Image,
+            Resource::Stylesheet => ResourceType::Stylesheet,
+            Resource::Font => ResourceType::Font,
+            Resource::Media => ResourceType::Media,
+        }
+    }
+}
+
+/// Drops non-essential requests so the registration reload only waits on what the flow reads
+pub struct ResourceBlocker {
+    patterns: Vec<RequestPattern>,
+    /// Urls containing any of these always load, e.g. the enrollment status icons
+    essential: Arc<Vec<String>>,
+    blocked: Arc<AtomicUsize>,
+}
+
+impl ResourceBlocker {
+    /// Blocks every request of the given types, or whose url matches one of `urls` where `*`
+    /// matches anything, except documents, XHR, fetch and anything in `essential`
+    pub fn new(resources: &[Resource], urls: &[String], essential: Vec<String>) -> Self {
+        let patterns = resources
+            .iter()
+            .map(|resource| {
+                RequestPattern::builder()
+                    .resource_type(ResourceType::from(*resource))
+                    .build()
+            })
+            .chain(
+                urls.iter()
+                    .map(|url| RequestPattern::builder().url_pattern(url).build()),
+            )
+            .collect();
+        Self {
+            patterns,
+            essential: Arc::new(essential),
+            blocked: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Starts blocking on `page`, once per page
+    pub async fn block(&self, page: &Page) -> Result<(), CdpError> {
+        if self.patterns.is_empty() {
+            return Ok(());
+        }
+        let mut paused = page.event_listener::<EventRequestPaused>().await?;
+        page.execute(
+            EnableParams::builder()
+                .patterns(self.patterns.clone())
+                .build(),
+        )
+        .await?;
+
+        let page = page.clone();
+        let essential = self.essential.clone();
+        let blocked = self.blocked.clone();
+        async_std::task::spawn(async move {
+            while let Some(event) = paused.next().await {
+                let needed = matches!(
+                    event.resource_type,
+                    ResourceType::Document | ResourceType::Xhr | ResourceType::Fetch
+                ) || essential
+                    .iter()
+                    .any(|url| event.request.url.contains(url.as_str()));
+                if !needed {
+                    blocked.fetch_add(1, Ordering::Relaxed);
+                }
+                // answer each request on its own so one slow reply holds up nothing else
+                let page = page.clone();
+                async_std::task::spawn(async move {
+                    // errors only mean the page has gone away
+                    let _ = if needed {
+                        page.execute(ContinueRequestParams::new(event.request_id.clone()))
+                            .await
+                            .map(|_| ())
+                    } else {
+                        page.execute(FailRequestParams::new(
+                            event.request_id.clone(),
+                            ErrorReason::BlockedByClient,
+                        ))
+                        .await
+                        .map(|_| ())
+                    };
+                });
+            }
+        });
+        Ok(())
+    }
+
+    /// Lets every request from `page` through again
+    pub async fn unblock(&self, page: &Page) -> Result<(), CdpError> {
+        if !self.patterns.is_empty() {
+            page.execute(DisableParams::default()).await?;
+        }
+        Ok(())
+    }
+
+    /// Requests dropped so far across every page
+    pub fn blocked(&self) -> usize {
+        self.blocked.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,358 @@
+use async_std::task::sleep;
+use chromiumoxide::cdp::browser_protocol::network::{
+    EventRequestWillBeSent, EventResponseReceived, RequestId, Response,
+};
+use chromiumoxide::error::CdpError;
+use chromiumoxide::Page;
+use chrono::{DateTime, Local, TimeDelta, Timelike, Utc};
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::schedule::wait_until;
+
+/// Probes stop once the skew is known this closely
+const TARGET_UNCERTAINTY_MS: i64 = 5;
+/// Most probes sent while waiting for one registration time
+const MAX_PROBES: usize = 20;
+/// Probing stops this long before registration so nothing competes with the reload
+pub const QUIET_PERIOD_SECS: i64 = 15;
+
+/// One portal response, timed on our clock and stamped by the server's
+struct Sample {
+    sent: DateTime<Utc>,
+    received: DateTime<Utc>,
+    /// HTTP `Date` header, truncated to the second by the server
+    date: DateTime<Utc>,
+}
+
+/// Estimate of how far the portal's clock is ahead of ours
+#[derive(Debug, Clone, Copy)]
+pub struct Skew {
+    /// Server time minus local time
+    pub offset: TimeDelta,
+    /// The true offset is within this much of `offset`
+    pub uncertainty: TimeDelta,
+    /// Median round trip of the sampled requests
+    pub round_trip: TimeDelta,
+    pub samples: usize,
+}
+
+impl fmt::Display for Skew {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "server clock {:+}ms ±{}ms, rtt {}ms ({} samples)",
+            self.offset.num_milliseconds(),
+            self.uncertainty.num_milliseconds(),
+            self.round_trip.num_milliseconds(),
+            self.samples
+        )
+    }
+}
+
+/// Estimates the portal's clock from the `Date` headers of its responses
+#[derive(Default)]
+pub struct ServerClock {
+    samples: Mutex<Vec<Sample>>,
+}
+
+impl ServerClock {
+    /// Records every response from `origin` that `page` receives from now on
+    pub async fn listen(self: &Arc<Self>, page: &Page, origin: &str) -> Result<(), CdpError> {
+        enum Event {
+            Sent(Arc<EventRequestWillBeSent>),
+            Received(Arc<EventResponseReceived>),
+        }
+
+        let sent = page
+            .event_listener::<EventRequestWillBeSent>()
+            .await?
+            .map(Event::Sent);
+        let received = page
+            .event_listener::<EventResponseReceived>()
+            .await?
+            .map(Event::Received);
+        let mut events = futures::stream::select(sent, received);
+        let clock = self.clone();
+        let origin = origin.to_string();
+
+        async_std::task::spawn(async move {
+            // request id -> (wall clock send time, monotonic send time in seconds)
+            let mut in_flight: HashMap<RequestId, (DateTime<Utc>, f64)> = HashMap::new();
+            while let Some(event) = events.next().await {
+                match event {
+                    Event::Sent(event) => {
+                        // a redirect reuses the request id and carries the previous response
+                        if let (Some(response), Some(&(sent, sent_at))) =
+                            (&event.redirect_response, in_flight.get(&event.request_id))
+                        {
+                            clock.record(
+                                &origin,
+                                response,
+                                sent,
+                                *event.timestamp.inner() - sent_at,
+                            );
+                        }
+                        let Some(sent) = DateTime::from_timestamp_micros(
+                            (*event.wall_time.inner() * 1_000_000.0) as i64,
+                        ) else {
+                            continue;
+                        };
+                        in_flight
+                            .insert(event.request_id.clone(), (sent, *event.timestamp.inner()));
+                    }
+                    Event::Received(event) => {
+                        if let Some((sent, sent_at)) = in_flight.remove(&event.request_id) {
+                            clock.record(
+                                &origin,
+                                &event.response,
+                                sent,
+                                *event.timestamp.inner() - sent_at,
+                            );
+                        }
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+
+    fn record(&self, origin: &str, response: &Response, sent: DateTime<Utc>, elapsed: f64) {
+        let cached = [
+            response.from_disk_cache,
+            response.from_prefetch_cache,
+            response.from_service_worker,
+        ]
+        .contains(&Some(true));
+        if cached || !response.url.starts_with(origin) || elapsed < 0.0 {
+            return;
+        }
+        let Some(date) = response
+            .headers
+            .inner()
+            .as_object()
+            .and_then(|headers| {
+                headers
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case("date"))
+            })
+            .and_then(|(_, date)| date.as_str())
+            .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+        else {
+            return;
+        };
+        self.samples.lock().unwrap().push(Sample {
+            sent,
+            received: sent + TimeDelta::microseconds((elapsed * 1_000_000.0) as i64),
+            date: date.to_utc(),
+        });
+    }
+
+    /// Current estimate, `None` until the portal has answered at least once.
+    ///
+    /// Each sample bounds the offset to `[date - received, date + 1s - sent]` and the estimate
+    /// is the middle of where all the bounds overlap.
+    pub fn skew(&self) -> Option<Skew> {
+        let samples = self.samples.lock().unwrap();
+        if samples.is_empty() {
+            return None;
+        }
+        let bounds: Vec<(TimeDelta, TimeDelta)> = samples
+            .iter()
+            .map(|sample| {
+                (
+                    sample.date - sample.received,
+                    sample.date + TimeDelta::seconds(1) - sample.sent,
+                )
+            })
+            .collect();
+        let low = bounds.iter().map(|(low, _)| *low).max().unwrap();
+        let high = bounds.iter().map(|(_, high)| *high).min().unwrap();
+        let (offset, uncertainty) = if low <= high {
+            ((low + high) / 2, (high - low) / 2)
+        } else {
+            // the samples disagree, e.g. servers behind a load balancer with different clocks,
+            // so fall back to the spread of every sample's midpoint
+            let mut middles: Vec<TimeDelta> = bounds
+                .iter()
+                .map(|(low, high)| (*low + *high) / 2)
+                .collect();
+            middles.sort();
+            let spread = *middles.last().unwrap() - middles[0];
+            (
+                middles[middles.len() / 2],
+                spread / 2 + TimeDelta::milliseconds(500),
+            )
+        };
+
+        let mut round_trips: Vec<TimeDelta> = samples
+            .iter()
+            .map(|sample| sample.received - sample.sent)
+            .collect();
+        round_trips.sort();
+        Some(Skew {
+            offset,
+            uncertainty,
+            round_trip: round_trips[round_trips.len() / 2],
+            samples: samples.len(),
+        })
+    }
+
+    /// Local time to send a request so it reaches the server just after `target` on its clock
+    pub fn fire_time(&self, target: DateTime<Local>) -> DateTime<Local> {
+        match self.skew() {
+            Some(skew) => target - skew.offset + skew.uncertainty - skew.round_trip / 2,
+            None => target,
+        }
+    }
+
+    /// Sends light requests timed to land on the server's second boundaries until the skew
+    /// is tight, probing stops well before `target` so the reload has the connection to itself
+    pub async fn sync(&self, page: &Page, target: DateTime<Local>) {
+        let quiet = target - TimeDelta::seconds(QUIET_PERIOD_SECS);
+        for _ in 0..MAX_PROBES {
+            let skew = self.skew();
+            if skew.is_some_and(|skew| skew.uncertainty.num_milliseconds() <= TARGET_UNCERTAINTY_MS)
+            {
+                break;
+            }
+            // land the probe on the next server second so the date flips as close to it as possible
+            let (offset, one_way) = skew
+                .map(|skew| (skew.offset, skew.round_trip / 2))
+                .unwrap_or_default();
+            let arrival = Local::now() + offset + one_way + TimeDelta::seconds(1);
+            let Some(boundary) = arrival.with_nanosecond(0) else {
+                break;
+            };
+            let send = boundary - offset - one_way;
+            if send >= quiet {
+                break;
+            }
+            wait_until(send).await;
+            if page
+                .evaluate_expression(
+                    "fetch(location.origin + '/favicon.ico', { method: 'HEAD', cache: 'no-store' })
+                        .then(() => true, () => false)",
+                )
+                .await
+                .is_err()
+            {
+                break;
+            }
+            // give the response event time to be recorded before the next estimate
+            sleep(Duration::from_secs(1)).await;
+        }
+    }
+}
+
+/// `scheme://host` of a url, which responses have to come from to be sampled
+pub fn origin(url: &str) -> &str {
+    let host_start = url.find("://").map(|scheme| scheme + 3).unwrap_or(0);
+    match url[host_start..].find('/') {
+        Some(path) => &url[..host_start + path],
+        None => url,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RTT_MS: i64 = 80;
+
+    /// Sample of a request sent at `sent_ms` past a local second to a server `offset_ms` ahead,
+    /// which stamps the response halfway through the round trip
+    fn sample(second: i64, sent_ms: i64, offset_ms: i64) -> Sample {
+        let sent = DateTime::from_timestamp(1_800_000_000 + second, 0).unwrap()
+            + TimeDelta::milliseconds(sent_ms);
+        let stamped = sent + TimeDelta::milliseconds(RTT_MS / 2 + offset_ms);
+        Sample {
+            sent,
+            received: sent + TimeDelta::milliseconds(RTT_MS),
+            date: stamped.with_nanosecond(0).unwrap(),
+        }
+    }
+
+    fn clock(samples: Vec<Sample>) -> ServerClock {
+        ServerClock {
+            samples: Mutex::new(samples),
+        }
+    }
+
+    #[test]
+    fn estimate_contains_the_true_offset() {
+        for offset_ms in [-2750, -40, 0, 380, 1234] {
+            let clock = clock(
+                [0, 130, 470, 610, 955]
+                    .iter()
+                    .enumerate()
+                    .map(|(second, sent_ms)| sample(second as i64 * 7, *sent_ms, offset_ms))
+                    .collect(),
+            );
+            let skew = clock.skew().unwrap();
+            let error = (skew.offset - TimeDelta::milliseconds(offset_ms)).abs();
+            assert!(error <= skew.uncertainty, "{offset_ms}ms: {skew}");
+            assert!(skew.uncertainty < TimeDelta::milliseconds(500), "{skew}");
+            assert_eq!(skew.round_trip, TimeDelta::milliseconds(RTT_MS));
+            assert_eq!(skew.samples, 5);
+        }
+    }
+
+    #[test]
+    fn more_samples_narrow_the_estimate() {
+        let one = clock(vec![sample(0, 300, 1234)]).skew().unwrap();
+        let many = clock((0..20).map(|i| sample(i * 3, i * 50, 1234)).collect())
+            .skew()
+            .unwrap();
+        assert!(many.uncertainty < one.uncertainty, "{many} vs {one}");
+    }
+
+    #[test]
+    fn falls_back_to_the_majority_when_the_samples_disagree() {
+        // two servers behind a load balancer three seconds apart, most answers from the first
+        let clock = clock(vec![
+            sample(0, 100, 0),
+            sample(5, 400, 3000),
+            sample(9, 700, 0),
+        ]);
+        let skew = clock.skew().unwrap();
+        assert!(skew.uncertainty >= TimeDelta::milliseconds(1500), "{skew}");
+        assert!(skew.offset.abs() <= skew.uncertainty, "{skew}");
+        assert_eq!(skew.samples, 3);
+    }
+
+    #[test]
+    fn fires_so_the_request_lands_after_the_target() {
+        let target = Local::now() + TimeDelta::minutes(5);
+        for offset_ms in [-2750, 0, 1234] {
+            let clock = clock((0..4).map(|i| sample(i * 11, i * 230, offset_ms)).collect());
+            let fire_time = clock.fire_time(target);
+            // the request reaches the server half a round trip later, read on its clock
+            let lands = fire_time
+                + TimeDelta::milliseconds(RTT_MS / 2)
+                + TimeDelta::milliseconds(offset_ms);
+            assert!(lands >= target, "{offset_ms}ms landed {}", lands - target);
+            assert!(lands - target <= TimeDelta::seconds(1), "{offset_ms}ms");
+        }
+    }
+
+    #[test]
+    fn fires_on_the_target_without_samples() {
+        let clock = ServerClock::default();
+        let target = Local::now();
+        assert!(clock.skew().is_none());
+        assert_eq!(clock.fire_time(target), target);
+    }
+
+    #[test]
+    fn takes_the_origin_of_a_url() {
+        assert_eq!(
+            origin("https://saprod.emory.edu/psc/saprod_48/EMPLOYEE/SA/c/X.GBL?a=b"),
+            "https://saprod.emory.edu"
+        );
+        assert_eq!(origin("http://127.0.0.1:8080"), "http://127.0.0.1:8080");
+    }
+}
@@ -0,0 +1,339 @@
+use chrono_tz::Tz;
+use inquire::{Password, PasswordDisplayMode, Text};
+use std::error::Error;
+use std::path::Path;
+use zeroize::Zeroizing;
+
+use crate::args::{Command, CredsCommand, PlanCommand, SessionCommand};
+use crate::creds::{self, PasswordSources, Vault, USER_ENV};
+use crate::elements::{ToOutput, ToTable};
+use crate::error::{Phase, SniperError};
+use crate::flow::{self, Sniper, Target};
+use crate::jar;
+use crate::plan::{Plan, PlanAction};
+use crate::schedule;
+
+/// Seconds `session save` waits for a login made by hand
+const MANUAL_LOGIN: u64 = 300;
+
+/// Catches missing or bad inputs before the browser is started, `attach` is whether there will
+/// be a window to log in by hand in
+pub fn check(
+    command: &Command,
+    plan: Option<&Plan>,
+    timezone: Option<Tz>,
+    sources: &PasswordSources,
+    attach: bool,
+) -> Result<(), SniperError> {
+    match command {
+        Command::DumpSelectors { .. }
+        | Command::Creds { .. }
+        | Command::CheckSession
+        | Command::Session {
+            command: SessionCommand::Load { .. },
+        } => (),
+        Command::Session {
+            command: SessionCommand::Save { user, .. },
+        } => match user {
+            Some(user) => sources.check(user)?,
+            None if !attach => Err(SniperError::Input(
+                "session save needs --user, or --attach to log in by hand".to_string(),
+            ))?,
+            None => (),
+        },
+        Command::ListCarts { login }
+        | Command::ListCourses { login, .. }
+        | Command::Validate { login, .. }
+        | Command::Watch { login, .. } => {
+            sources.check(&login.user)?;
+        }
+        Command::Enroll {
+            login,
+            courses,
+            fallbacks,
+            at,
+            ..
+        } => {
+            sources.check(&login.user)?;
+            schedule::registration_time(*at, timezone)?;
+            for (class, _) in fallbacks {
+                if !courses.classes.contains(class) {
+                    Err(SniperError::Input(format!(
+                        "--fallback is for \"{class}\" which is not one of the --class values"
+                    )))?
+                }
+            }
+        }
+        Command::Run { .. } | Command::Plan { .. } => {
+            let plan = plan.ok_or(SniperError::Input("plan was not loaded".to_string()))?;
+            if plan.action == PlanAction::Enroll {
+                schedule::registration_time(plan.at, timezone)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs a scripted subcommand against the already opened portal page
+pub async fn execute(
+    sniper: &Sniper<'_>,
+    command: &Command,
+    plan: Option<&Plan>,
+    timezone: Option<Tz>,
+    sources: &PasswordSources,
+) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::DumpSelectors { .. } | Command::Creds { .. } => {
+            unreachable!("handled before the browser starts")
+        }
+        // a loaded session file was restored before the portal opened
+        Command::CheckSession
+        | Command::Session {
+            command: SessionCommand::Load { .. },
+        } => {
+            if !sniper.logged_in().await? {
+                Err(SniperError::SessionExpired(Phase::Session))?
+            }
+            println!("The saved session is still logged in.");
+        }
+        Command::ListCarts { login } => {
+            sniper
+                .login(&login.user, &sources.password(&login.user)?)
+                .await?;
+            let carts = sniper.find_carts().await?.unwrap_or_else(|| {
+                eprintln!("The account has a single shopping cart which opens directly.");
+                Vec::new()
+            });
+            print(&carts, sniper)?;
+        }
+        Command::ListCourses { login, cart } => {
+            sniper
+                .login(&login.user, &sources.password(&login.user)?)
+                .await?;
+            sniper.open_cart(cart.term.as_deref()).await?;
+            print(&sniper.get_courses().await?, sniper)?;
+        }
+        Command::Validate {
+            login,
+            cart,
+            courses,
+        } => {
+            sniper
+                .login(&login.user, &sources.password(&login.user)?)
+                .await?;
+            sniper.open_cart(cart.term.as_deref()).await?;
+            let selected_courses =
+                flow::match_courses(&sniper.get_courses().await?, &courses.classes)?;
+            let registration_results = sniper.validate(&selected_courses).await?;
+            print(&registration_results, sniper)?;
+        }
+        Command::Enroll {
+            login,
+            cart,
+            courses,
+            fallbacks,
+            at,
+        } => {
+            let registration_time = schedule::registration_time(*at, timezone)?;
+            sniper
+                .login(&login.user, &sources.password(&login.user)?)
+                .await?;
+            sniper.open_cart(cart.term.as_deref()).await?;
+            let cart_courses = sniper.get_courses().await?;
+            let targets = courses
+                .classes
+                .iter()
+                .map(|class| {
+                    let alternates: Vec<String> = fallbacks
+                        .iter()
+                        .filter(|(fallback_class, _)| fallback_class == class)
+                        .flat_map(|(_, alternates)| alternates.clone())
+                        .collect();
+                    Ok(Target {
+                        course: flow::match_course(&cart_courses, class)?,
+                        fallbacks: flow::match_courses(&cart_courses, &alternates)?,
+                    })
+                })
+                .collect::<Result<Vec<Target>, SniperError>>()?;
+            let wanted = targets.len();
+            let registration_results = sniper
+                .enroll_with_fallbacks(targets, registration_time)
+                .await?;
+            print(&registration_results, sniper)?;
+            SniperError::check_enrollment(&registration_results, wanted)?;
+        }
+        Command::Watch {
+            login,
+            cart,
+            courses,
+            interval,
+            max_attempts,
+            max_polls,
+        } => {
+            sniper
+                .login(&login.user, &sources.password(&login.user)?)
+                .await?;
+            sniper.open_cart(cart.term.as_deref()).await?;
+            let watched = flow::match_courses(&sniper.get_courses().await?, &courses.classes)?;
+            let wanted = watched.len();
+            let registration_results = sniper
+                .watch(watched, *interval, *max_attempts, *max_polls)
+                .await?;
+            print(&registration_results, sniper)?;
+            SniperError::check_enrollment(&registration_results, wanted)?;
+        }
+        Command::Run { .. } => {
+            let plan = plan.ok_or("plan was not loaded")?;
+            let (username, password) = plan_credentials(plan, sources)?;
+            sniper.login(&username, &password).await?;
+            sniper.open_cart(plan.term.as_deref()).await?;
+            let courses = sniper.get_courses().await?;
+            sniper.note(courses.to_table());
+            let targets = plan.targets(&courses)?;
+            let wanted = targets.len();
+            let registration_results = match plan.action {
+                PlanAction::Validate => {
+                    let courses: Vec<_> = targets.into_iter().map(|target| target.course).collect();
+                    sniper.validate(&courses).await?
+                }
+                PlanAction::Enroll => {
+                    sniper
+                        .enroll_with_fallbacks(
+                            targets,
+                            schedule::registration_time(plan.at, timezone)?,
+                        )
+                        .await?
+                }
+            };
+            print(&registration_results, sniper)?;
+            if plan.action == PlanAction::Enroll {
+                SniperError::check_enrollment(&registration_results, wanted)?;
+            }
+        }
+        Command::Session {
+            command: SessionCommand::Save { file, user },
+        } => {
+            let passphrase = jar::passphrase(true)?;
+            match user {
+                Some(user) => sniper.login(user, &sources.password(user)?).await?,
+                None => sniper.wait_for_login(MANUAL_LOGIN).await?,
+            }
+            let count = jar::save(
+                sniper.browser,
+                sniper.page,
+                sniper.school.page_url(),
+                file,
+                &passphrase,
+            )
+            .await?;
+            println!("Saved {count} cookies to {}.", file.display());
+        }
+        Command::Plan {
+            command: PlanCommand::Check { .. },
+        } => {
+            let plan = plan.ok_or("plan was not loaded")?;
+            let (username, password) = plan_credentials(plan, sources)?;
+            sniper.login(&username, &password).await?;
+            sniper.open_cart(plan.term.as_deref()).await?;
+            let courses = sniper.get_courses().await?;
+            println!("{}", courses.to_table());
+
+            let mut problems = 0;
+            for planned in &plan.courses {
+                for (kind, pattern) in std::iter::once(("course", &planned.class)).chain(
+                    planned
+                        .fallbacks
+                        .iter()
+                        .map(|fallback| ("fallback", fallback)),
+                ) {
+                    match flow::match_course(&courses, pattern) {
+                        Ok(course) => println!(
+                            "✅ {kind} \"{pattern}\" -> {course} ({})",
+                            course.availability
+                        ),
+                        Err(e) => {
+                            println!("❌ {kind} \"{pattern}\": {e}");
+                            problems += 1;
+                        }
+                    }
+                }
+            }
+            if problems > 0 {
+                Err(SniperError::CourseMissing(format!(
+                    "plan check found {problems} problems"
+                )))?
+            }
+            println!("Plan is ready.");
+        }
+    }
+    Ok(())
+}
+
+/// Prints carts, courses or results to stdout in the `--output` format
+fn print(rows: &impl ToOutput, sniper: &Sniper<'_>) -> Result<(), Box<dyn Error>> {
+    let output = rows.to_output(sniper.output)?;
+    if !output.is_empty() {
+        println!("{output}");
+    }
+    Ok(())
+}
+
+/// Plans leave the password out, so it comes from the usual sources and the username from the
+/// environment or a prompt when the plan has none
+fn plan_credentials(
+    plan: &Plan,
+    sources: &PasswordSources,
+) -> Result<(String, Zeroizing<String>), Box<dyn Error>> {
+    let username = match plan.username.clone().or(std::env::var(USER_ENV).ok()) {
+        Some(username) => username,
+        None => Text::new("Username: ").prompt()?,
+    };
+    let password = sources.password(&username)?;
+    Ok((username, password))
+}
+
+/// Runs a `creds` subcommand against the vault at `path`, no browser needed
+pub fn creds(command: &CredsCommand, path: &Path, password_stdin: bool) -> Result<(), SniperError> {
+    let passphrase = creds::vault_passphrase(!path.exists())?;
+    let mut vault = Vault::open(path, &passphrase)?;
+    match command {
+        CredsCommand::Add { user } => {
+            let password = if password_stdin {
+                creds::read_stdin_line()?
+            } else {
+                Password::new(&format!("Password for {user}:"))
+                    .with_display_mode(PasswordDisplayMode::Masked)
+                    .prompt()
+                    .map(Zeroizing::new)
+                    .map_err(|_| {
+                        SniperError::Input(format!(
+                            "no password for {user}, pass --password-stdin to run without prompts"
+                        ))
+                    })?
+            };
+            let replaced = vault.add(user, &password);
+            vault.save(path, &passphrase)?;
+            if replaced {
+                println!("Updated the password for {user} in {}.", path.display());
+            } else {
+                println!("Added {user} to {}.", path.display());
+            }
+        }
+        CredsCommand::List => {
+            for username in vault.usernames() {
+                println!("{username}");
+            }
+        }
+        CredsCommand::Remove { user } => {
+            if !vault.remove(user) {
+                Err(SniperError::Input(format!(
+                    "{user} is not in {}",
+                    path.display()
+                )))?
+            }
+            vault.save(path, &passphrase)?;
+            println!("Removed {user} from {}.", path.display());
+        }
+    }
+    Ok(())
+}
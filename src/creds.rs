@@ -0,0 +1,248 @@
+use inquire::{Password, PasswordDisplayMode};
+use serde::{Deserialize, Serialize};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::error::SniperError;
+use crate::seal;
+
+pub const USER_ENV: &str = "COURSE_SNIPER_USER";
+pub const PASSWORD_ENV: &str = "COURSE_SNIPER_PASSWORD";
+const VAULT_KEY_ENV: &str = "COURSE_SNIPER_VAULT_KEY";
+
+/// Where the vault lives unless `--vault` says otherwise
+pub fn default_vault() -> PathBuf {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .or_else(|| std::env::var_os("APPDATA"))
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_default()
+        .join("course-sniper")
+        .join("credentials.vault")
+}
+
+/// Whether someone is at the terminal to answer a prompt
+fn interactive() -> bool {
+    std::io::stdin().is_terminal()
+}
+
+/// Asks for a secret without echoing it, `None` when nobody is at the terminal
+fn prompt_secret(message: &str, confirm: bool) -> Option<Zeroizing<String>> {
+    if !interactive() {
+        return None;
+    }
+    let prompt = Password::new(message).with_display_mode(PasswordDisplayMode::Masked);
+    let prompt = if confirm {
+        prompt
+    } else {
+        prompt.without_confirmation()
+    };
+    prompt.prompt().ok().map(Zeroizing::new)
+}
+
+/// Reads the first line of stdin, for `--password-stdin`
+pub fn read_stdin_line() -> Result<Zeroizing<String>, SniperError> {
+    let mut line = Zeroizing::new(String::new());
+    std::io::stdin()
+        .read_line(&mut line)
+        .map_err(|e| SniperError::Input(format!("could not read the password from stdin: {e}")))?;
+    let password = Zeroizing::new(line.trim_end_matches(['\r', '\n']).to_string());
+    if password.is_empty() {
+        Err(SniperError::Input(
+            "stdin did not have a password".to_string(),
+        ))?
+    }
+    Ok(password)
+}
+
+/// Master passphrase of the vault, from the environment or asked for
+pub fn vault_passphrase(confirm: bool) -> Result<Zeroizing<String>, SniperError> {
+    if let Ok(passphrase) = std::env::var(VAULT_KEY_ENV) {
+        return Ok(Zeroizing::new(passphrase));
+    }
+    prompt_secret("Vault passphrase:", confirm).ok_or(SniperError::Input(format!(
+        "no vault passphrase, set {VAULT_KEY_ENV} to run without prompts"
+    )))
+}
+
+/// Places a login password can come from, tried in the order of the fields
+pub struct PasswordSources {
+    /// Read the first line of stdin, for `--password-stdin`
+    pub stdin: bool,
+    /// Vault to look the username up in, skipped when the file does not exist
+    pub vault: PathBuf,
+}
+
+impl PasswordSources {
+    /// Password for `user` from stdin, `COURSE_SNIPER_PASSWORD`, the vault, or a prompt
+    pub fn password(&self, user: &str) -> Result<Zeroizing<String>, SniperError> {
+        if self.stdin {
+            return read_stdin_line();
+        }
+        if let Ok(password) = std::env::var(PASSWORD_ENV) {
+            return Ok(Zeroizing::new(password));
+        }
+        if self.vault.exists() {
+            let vault = Vault::open(&self.vault, &vault_passphrase(false)?)?;
+            if let Some(password) = vault.password(user) {
+                return Ok(password);
+            }
+        }
+        prompt_secret(&format!("Password for {user}:"), false).ok_or_else(|| self.missing(user))
+    }
+
+    /// Errors before the browser starts when nothing could give `user` a password
+    pub fn check(&self, user: &str) -> Result<(), SniperError> {
+        if self.stdin || std::env::var(PASSWORD_ENV).is_ok() || interactive() {
+            return Ok(());
+        }
+        // without a prompt the vault can only be opened with the passphrase from the environment
+        if self.vault.exists() && std::env::var(VAULT_KEY_ENV).is_ok() {
+            let vault = Vault::open(&self.vault, &vault_passphrase(false)?)?;
+            if vault.password(user).is_some() {
+                return Ok(());
+            }
+        }
+        Err(self.missing(user))
+    }
+
+    fn missing(&self, user: &str) -> SniperError {
+        SniperError::Input(format!(
+            "no password for {user}, set {PASSWORD_ENV}, pass --password-stdin, or add it to {} with `creds add`",
+            self.vault.display()
+        ))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct VaultEntry {
+    username: String,
+    password: String,
+}
+
+impl Drop for VaultEntry {
+    fn drop(&mut self) {
+        self.password.zeroize();
+    }
+}
+
+/// Usernames and passwords kept in a local file sealed with a master passphrase
+#[derive(Serialize, Deserialize, Default)]
+pub struct Vault {
+    entries: Vec<VaultEntry>,
+}
+
+impl Vault {
+    /// Opens the vault at `path`, which is empty when the file does not exist yet
+    pub fn open(path: &Path, passphrase: &str) -> Result<Self, SniperError> {
+        if !path.exists() {
+            return Ok(Vault::default());
+        }
+        let sealed = std::fs::read(path)
+            .map_err(|e| SniperError::Input(format!("could not read {}: {e}", path.display())))?;
+        let json = Zeroizing::new(seal::open(&sealed, passphrase).ok_or_else(|| {
+            SniperError::Input(format!(
+                "could not unlock {}, the passphrase is wrong or it is not a vault",
+                path.display()
+            ))
+        })?);
+        serde_json::from_slice(&json)
+            .map_err(|e| SniperError::Input(format!("{} is damaged: {e}", path.display())))
+    }
+
+    /// Seals the vault to `path`, creating its directory if needed
+    pub fn save(&self, path: &Path, passphrase: &str) -> Result<(), SniperError> {
+        let write_error = |e: std::io::Error| {
+            SniperError::Input(format!("could not write {}: {e}", path.display()))
+        };
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(write_error)?;
+        }
+        let json = Zeroizing::new(
+            serde_json::to_vec(self)
+                .map_err(|e| SniperError::Input(format!("could not write the vault: {e}")))?,
+        );
+        std::fs::write(path, seal::seal(&json, passphrase)).map_err(write_error)
+    }
+
+    /// Stores the password for `username`, returns whether it replaced one
+    pub fn add(&mut self, username: &str, password: &str) -> bool {
+        let replaced = self.remove(username);
+        self.entries.push(VaultEntry {
+            username: username.to_string(),
+            password: password.to_string(),
+        });
+        replaced
+    }
+
+    /// Forgets `username`, returns whether it was there
+    pub fn remove(&mut self, username: &str) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|entry| entry.username != username);
+        self.entries.len() != before
+    }
+
+    pub fn usernames(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|entry| entry.username.as_str())
+    }
+
+    pub fn password(&self, username: &str) -> Option<Zeroizing<String>> {
+        self.entries
+            .iter()
+            .find(|entry| entry.username == username)
+            .map(|entry| Zeroizing::new(entry.password.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_replaces_and_removes_passwords() {
+        let mut vault = Vault::default();
+        assert!(!vault.add("jdoe", "first"));
+        assert!(!vault.add("asmith", "second"));
+        assert!(vault.add("jdoe", "third"));
+        assert_eq!(
+            vault.usernames().collect::<Vec<_>>(),
+            vec!["asmith", "jdoe"]
+        );
+        assert_eq!(
+            vault.password("jdoe").as_deref().map(String::as_str),
+            Some("third")
+        );
+        assert!(vault.remove("jdoe"));
+        assert!(!vault.remove("jdoe"));
+        assert!(vault.password("jdoe").is_none());
+        assert_eq!(vault.usernames().collect::<Vec<_>>(), vec!["asmith"]);
+    }
+
+    #[test]
+    fn saves_and_opens_a_sealed_vault() {
+        let path = std::env::temp_dir().join(format!(
+            "course-sniper-test-{}-sealed.vault",
+            std::process::id()
+        ));
+        // everything is read before asserting so the file is removed even when one fails
+        let empty = Vault::open(&path, "key").map(|vault| vault.usernames().count());
+        let mut vault = Vault::default();
+        vault.add("jdoe", "hunter2");
+        let saved = vault.save(&path, "key");
+        let sealed = std::fs::read(&path);
+        let opened = Vault::open(&path, "key").map(|vault| vault.password("jdoe"));
+        let wrong = Vault::open(&path, "not the key").err();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(empty.unwrap(), 0);
+        saved.unwrap();
+        assert!(!String::from_utf8_lossy(&sealed.unwrap()).contains("hunter2"));
+        assert_eq!(
+            opened.unwrap().as_deref().map(String::as_str),
+            Some("hunter2")
+        );
+        let wrong = wrong.unwrap().to_string();
+        assert!(wrong.contains("could not unlock"), "{wrong}");
+    }
+}
@@ -1,70 +1,119 @@
-use chromiumoxide::{error::CdpError, Element, Page};
+use crate::school::PeopleSoftConfig;
+use chromiumoxide::Element;
+use clap::ValueEnum;
 use comfy_table::{Attribute, Cell, Color, Table};
+use serde::{Deserialize, Serialize, Serializer};
 use std::fmt;
+use std::path::Path;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct EmoryPageElements {
-    pub page_url: &'static str,
-    pub username_input: &'static str,
-    pub passwd_input: &'static str,
-    pub login_error: &'static str,
-    pub validate_button: &'static str,
-    pub enroll_button: &'static str,
-    pub enroll_confirm_button: &'static str,
-    pub semester_cart: &'static str,
-    pub course_row: &'static str,
-    pub checkboxes: &'static str,
-    pub availability: &'static str,
-    pub description: &'static str,
-    pub schedule: &'static str,
-    pub room: &'static str,
-    pub instructor: &'static str,
-    pub credits: &'static str,
-    pub seats: &'static str,
-    pub results_rows: &'static str,
-    pub result_description: &'static str,
-    pub result_status: &'static str,
-    pub registration_success: &'static str,
-    pub registration_fail: &'static str,
-    pub duo_waiting: &'static str,
-    pub duo_trust_browser: &'static str,
-    pub duo_time_out_try_again: &'static str,
+    pub page_url: String,
+    pub username_input: String,
+    pub passwd_input: String,
+    pub login_error: String,
+    pub session_timeout: String,
+    pub validate_button: String,
+    pub enroll_button: String,
+    pub enroll_confirm_button: String,
+    pub semester_cart: String,
+    pub course_row: String,
+    pub class_number: String,
+    pub checkboxes: String,
+    pub availability: String,
+    pub description: String,
+    pub schedule: String,
+    pub room: String,
+    pub instructor: String,
+    pub credits: String,
+    pub seats: String,
+    pub results_rows: String,
+    pub result_description: String,
+    pub result_status: String,
+    pub registration_success: String,
+    pub registration_fail: String,
+    pub duo_waiting: String,
+    pub duo_trust_browser: String,
+    pub duo_time_out_try_again: String,
+    pub duo_other_options: String,
+    pub duo_passcode_option: String,
+    pub duo_passcode_input: String,
+    pub duo_passcode_submit: String,
+    pub duo_passcode_error: String,
 }
 
 impl Default for EmoryPageElements {
     fn default() -> Self {
-        Self {
-            page_url: "https://saprod.emory.edu/psc/saprod_48/EMPLOYEE/SA/c/SSR_STUDENT_FL.SSR_SHOP_CART_FL.GBL",
-            username_input: "input#userid",
-            passwd_input: "input#pwd",
-            login_error: "div#ptloginerrorcont",
-            validate_button: "a#DERIVED_SSR_FL_SSR_VALIDATE_FL",
-            enroll_button: "a#DERIVED_SSR_FL_SSR_ENROLL_FL",
-            enroll_confirm_button: r#"a[id="\#ICYes"]"#,
-            semester_cart: r#"a[id^="SSR_CART_TRM_FL_TERM_DESCR30$"]"#,
-            course_row: r#"tr[id^="SSR_REGFORM_VW$0_row_"]"#,
-            checkboxes: r#"input[type="checkbox"][id^="DERIVED_REGFRM1_SSR_SELECT$"]"#,
-            availability: r#"span[id^="DERIVED_SSR_FL_SSR_AVAIL_FL$"]"#,
-            description: r#"span[id^="DERIVED_SSR_FL_SSR_DESCR80$"]"#,
-            schedule: r#"span[id^="DERIVED_REGFRM1_SSR_MTG_SCHED_LONG$"]"#,
-            room: r#"span[id^="DERIVED_REGFRM1_SSR_MTG_LOC_LONG$"]"#,
-            instructor: r#"span[id^="DERIVED_REGFRM1_SSR_INSTR_LONG$"]"#,
-            credits: r#"span[id^="DERIVED_SSR_FL_SSR_UNITS_LBL$"]"#,
-            seats: r#"span[id^="DERIVED_SSR_FL_SSR_DESCR50$"]"#,
-            results_rows: r#"div[id^="win48div$ICField229_row$"]"#,
-            result_description: r#"span[id^="DERIVED_REGFRM1_DESCRLONG$"]"#,
-            result_status: r#"div[id^="win48divDERIVED_REGFRM1_SSR_STATUS_LONG$"]"#,
-            registration_success: "/cs/saprod/cache/PS_CS_STATUS_SUCCESS_ICN_1.gif",
-            registration_fail: "/cs/saprod/cache/PS_CS_STATUS_ERROR_ICN_1.gif",
-            duo_waiting: "div#auth-view-wrapper:not(.auth-error)",
-            duo_trust_browser: r#"button[id="trust-browser-button"]"#,
-            duo_time_out_try_again: r#"button.try-again-button"#
+        PeopleSoftConfig::emory().elements()
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectorFormat {
+    Toml,
+    Json,
+}
+
+/// How carts, courses and results are printed, everything but `table` is meant for scripts
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Table,
+    /// One JSON array
+    Json,
+    /// One JSON object per line
+    Ndjson,
+    Csv,
+}
+
+impl EmoryPageElements {
+    /// Reads a selector file, any field it leaves out is taken from `defaults`.
+    ///
+    /// The format is picked from the file extension, anything other than `.json` is read as TOML.
+    pub fn from_file(
+        path: &Path,
+        defaults: EmoryPageElements,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(path)?;
+        let overrides: serde_json::Map<String, serde_json::Value> =
+            match SelectorFormat::from_path(path) {
+                SelectorFormat::Json => serde_json::from_str(&contents)?,
+                SelectorFormat::Toml => toml::from_str(&contents)?,
+            };
+
+        let mut merged = match serde_json::to_value(defaults)? {
+            serde_json::Value::Object(map) => map,
+            _ => unreachable!("page elements always serialize to a map"),
+        };
+        merged.extend(overrides);
+        Ok(serde_json::from_value(serde_json::Value::Object(merged))?)
+    }
+
+    pub fn to_string_pretty(
+        &self,
+        format: SelectorFormat,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(match format {
+            SelectorFormat::Toml => toml::to_string_pretty(self)?,
+            SelectorFormat::Json => serde_json::to_string_pretty(self)?,
+        })
+    }
+}
+
+impl SelectorFormat {
+    fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => SelectorFormat::Json,
+            _ => SelectorFormat::Toml,
         }
     }
 }
 
+#[derive(Serialize)]
 pub struct ShoppingCart {
+    #[serde(skip)]
     pub element: Element,
+    #[serde(rename = "term", serialize_with = "collapsed")]
     pub text: String,
 }
 
@@ -74,7 +123,8 @@ impl fmt::Display for ShoppingCart {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
 pub enum CourseStatus {
     Waitlist { position: u32 },
     Open { available: u32, capacity: u32 },
@@ -94,158 +144,56 @@ impl fmt::Display for CourseStatus {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Course {
-    pub checkbox_index: u8,
+    /// PeopleSoft class number, `None` when the row does not show one
+    pub class_number: Option<String>,
     pub availability: CourseStatus,
+    #[serde(serialize_with = "collapsed")]
     pub description: String,
+    #[serde(serialize_with = "collapsed")]
     pub schedule: String,
+    #[serde(serialize_with = "collapsed")]
     pub room: String,
+    #[serde(serialize_with = "collapsed")]
     pub instructor: String,
     pub credits: String,
 }
 
-impl fmt::Display for Course {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.description)
+impl Course {
+    /// Whether `pattern` is exactly the class number or appears in the course description,
+    /// ignoring case
+    pub fn matches(&self, pattern: &str) -> bool {
+        let pattern = pattern.trim();
+        match &self.class_number {
+            Some(class_number) if pattern.chars().all(|c| c.is_ascii_digit()) => {
+                class_number == pattern
+            }
+            _ => self
+                .description
+                .to_lowercase()
+                .contains(&pattern.to_lowercase()),
+        }
     }
-}
 
-impl EmoryPageElements {
-    pub async fn get_shopping_carts(&self, page: &Page) -> Result<Vec<ShoppingCart>, CdpError> {
-        let semester_cart_elements = page.find_elements(self.semester_cart).await?;
-        let semester_carts: Vec<ShoppingCart> =
-            futures::future::join_all(semester_cart_elements.into_iter().map(|cart| async move {
-                let text = cart.inner_text().await.unwrap().expect("test");
-                ShoppingCart {
-                    element: cart,
-                    text,
-                }
-            }))
-            .await;
-        Ok(semester_carts)
+    /// Whether a cart row read as `class_number` and `description` is this course,
+    /// by class number when both have one and by description otherwise
+    pub fn is_row(&self, class_number: Option<&str>, description: &str) -> bool {
+        match (self.class_number.as_deref(), class_number) {
+            (Some(ours), Some(theirs)) => ours == theirs,
+            _ => self.description == description,
+        }
     }
 
-    pub async fn get_cart_courses(&self, page: &Page) -> Result<Vec<Course>, CdpError> {
-        let course_row_elements = page.find_elements(self.course_row).await?;
-        let courses: Vec<Course> =
-            futures::future::try_join_all(course_row_elements.into_iter().enumerate().map(
-                |(index, row)| async move {
-                    let nums: Vec<u32> = row
-                        .find_element(self.seats)
-                        .await?
-                        .inner_text()
-                        .await?
-                        .unwrap_or("".to_string())
-                        .split_whitespace()
-                        .filter_map(|word| word.parse().ok())
-                        .collect();
-
-                    let course_status = match row
-                        .find_element(self.availability)
-                        .await?
-                        .inner_text()
-                        .await?
-                        .unwrap_or("".to_string())
-                    {
-                        text if text.contains("Wait List") => {
-                            if nums.len() == 2 {
-                                CourseStatus::Waitlist {
-                                    position: nums[1] - nums[0],
-                                }
-                            } else {
-                                CourseStatus::Waitlist { position: 999 }
-                            }
-                        }
-                        text if text.contains("Closed") => CourseStatus::Closed,
-                        text if text.contains("Open") => {
-                            if nums.len() == 2 {
-                                CourseStatus::Open {
-                                    available: nums[0],
-                                    capacity: nums[1],
-                                }
-                            } else {
-                                CourseStatus::Open {
-                                    available: 0,
-                                    capacity: 0,
-                                }
-                            }
-                        }
-                        _ => CourseStatus::Closed,
-                    };
-
-                    Ok::<Course, CdpError>(Course {
-                        checkbox_index: index as u8,
-                        availability: course_status,
-                        description: row
-                            .find_element(self.description)
-                            .await?
-                            .inner_text()
-                            .await?
-                            .unwrap_or("None".to_string()),
-                        schedule: row
-                            .find_element(self.schedule)
-                            .await?
-                            .inner_text()
-                            .await?
-                            .unwrap_or("None".to_string()),
-                        instructor: row
-                            .find_element(self.instructor)
-                            .await?
-                            .inner_text()
-                            .await?
-                            .unwrap_or("None".to_string()),
-                        room: row
-                            .find_element(self.room)
-                            .await?
-                            .inner_text()
-                            .await?
-                            .unwrap_or("None".to_string()),
-                        credits: row
-                            .find_element(self.credits)
-                            .await?
-                            .inner_text()
-                            .await?
-                            .unwrap_or("None".to_string()),
-                    })
-                },
-            ))
-            .await?;
-
-        Ok(courses)
+    /// Whether `other` is the same class, possibly read from a reloaded cart
+    pub fn same_class(&self, other: &Course) -> bool {
+        self.is_row(other.class_number.as_deref(), &other.description)
     }
+}
 
-    pub async fn get_registration_results(
-        &self,
-        page: &Page,
-    ) -> Result<Vec<RegistrationResult>, CdpError> {
-        let result_elements = page.find_elements(self.results_rows).await?;
-        let results: Vec<RegistrationResult> =
-            futures::future::try_join_all(result_elements.into_iter().map(|result| async move {
-                let status_html = result
-                    .find_element(self.result_status)
-                    .await?
-                    .inner_html()
-                    .await?
-                    .unwrap_or("".to_string());
-                Ok::<RegistrationResult, CdpError>(RegistrationResult {
-                    description: result
-                        .find_element(self.result_description)
-                        .await?
-                        .inner_text()
-                        .await?
-                        .unwrap_or("None".to_string()),
-                    status: if status_html.contains(self.registration_success) {
-                        RegistrationStatus::Success
-                    } else if status_html.contains(self.registration_fail) {
-                        RegistrationStatus::Fail
-                    } else {
-                        RegistrationStatus::Unknown
-                    },
-                })
-            }))
-            .await?;
-        Ok(results)
+impl fmt::Display for Course {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.description)
     }
 }
 
@@ -257,6 +205,7 @@ impl ToTable for Vec<Course> {
     fn to_table(&self) -> Table {
         let mut table = Table::new();
         table.set_header(vec![
+            Cell::new("Class"),
             Cell::new("Course").add_attribute(Attribute::Bold),
             Cell::new("Credits"),
             Cell::new("Availability").add_attribute(Attribute::Bold),
@@ -267,6 +216,7 @@ impl ToTable for Vec<Course> {
 
         for course in self {
             table.add_row(vec![
+                Cell::new(course.class_number.as_deref().unwrap_or("-")),
                 Cell::new(course.description.clone()).fg(Color::Green),
                 Cell::new(course.credits.clone()),
                 Cell::new(course.availability.to_string()).fg(Color::Green),
@@ -285,6 +235,8 @@ impl ToTable for Vec<Course> {
     }
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum RegistrationStatus {
     Success,
     Fail,
@@ -301,11 +253,32 @@ impl fmt::Display for RegistrationStatus {
     }
 }
 
+#[derive(Serialize)]
 pub struct RegistrationResult {
+    #[serde(serialize_with = "collapsed")]
     pub description: String,
     pub status: RegistrationStatus,
 }
 
+impl RegistrationResult {
+    /// Whether this result row is about `course`, comparing descriptions with whitespace collapsed
+    pub fn is_for(&self, course: &Course) -> bool {
+        let result = self
+            .description
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ");
+        let course = course
+            .description
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ");
+        !result.is_empty()
+            && !course.is_empty()
+            && (result.contains(&course) || course.contains(&result))
+    }
+}
+
 impl ToTable for Vec<RegistrationResult> {
     fn to_table(&self) -> Table {
         let mut table = Table::new();
@@ -327,3 +300,186 @@ impl ToTable for Vec<RegistrationResult> {
         table
     }
 }
+
+/// Text scraped from the portal with its runs of whitespace collapsed, as the tables show it
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+fn collapsed<S: Serializer>(text: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&collapse(text))
+}
+
+/// Flat row of a CSV export, nested fields like a course's availability get their own columns
+pub trait ToRecord {
+    const HEADER: &'static [&'static str];
+    fn to_record(&self) -> Vec<String>;
+}
+
+impl ToRecord for ShoppingCart {
+    const HEADER: &'static [&'static str] = &["term"];
+
+    fn to_record(&self) -> Vec<String> {
+        vec![collapse(&self.text)]
+    }
+}
+
+impl ToRecord for Course {
+    const HEADER: &'static [&'static str] = &[
+        "class_number",
+        "description",
+        "credits",
+        "status",
+        "available",
+        "capacity",
+        "waitlist_position",
+        "schedule",
+        "room",
+        "instructor",
+    ];
+
+    fn to_record(&self) -> Vec<String> {
+        let (status, available, capacity, position) = match &self.availability {
+            CourseStatus::Open {
+                available,
+                capacity,
+            } => (
+                "open",
+                available.to_string(),
+                capacity.to_string(),
+                String::new(),
+            ),
+            CourseStatus::Waitlist { position } => (
+                "waitlist",
+                String::new(),
+                String::new(),
+                position.to_string(),
+            ),
+            CourseStatus::Closed => ("closed", String::new(), String::new(), String::new()),
+        };
+        vec![
+            self.class_number.clone().unwrap_or_default(),
+            collapse(&self.description),
+            self.credits.clone(),
+            status.to_string(),
+            available,
+            capacity,
+            position,
+            collapse(&self.schedule),
+            collapse(&self.room),
+            collapse(&self.instructor),
+        ]
+    }
+}
+
+impl ToRecord for RegistrationResult {
+    const HEADER: &'static [&'static str] = &["description", "status"];
+
+    fn to_record(&self) -> Vec<String> {
+        let status = match self.status {
+            RegistrationStatus::Success => "success",
+            RegistrationStatus::Fail => "fail",
+            RegistrationStatus::Unknown => "unknown",
+        };
+        vec![collapse(&self.description), status.to_string()]
+    }
+}
+
+/// Carts, courses or results printed in the `--output` format
+pub trait ToOutput {
+    fn to_output(&self, format: OutputFormat) -> Result<String, Box<dyn std::error::Error>>;
+}
+
+/// Prints `rows` in one of the machine readable formats
+fn to_structured<T: Serialize + ToRecord>(
+    rows: &[T],
+    format: OutputFormat,
+) -> Result<String, Box<dyn std::error::Error>> {
+    Ok(match format {
+        OutputFormat::Table => unreachable!("tables are printed by each type"),
+        OutputFormat::Json => serde_json::to_string_pretty(rows)?,
+        OutputFormat::Ndjson => rows
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<Result<Vec<String>, _>>()?
+            .join("\n"),
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            writer.write_record(T::HEADER)?;
+            for row in rows {
+                writer.write_record(row.to_record())?;
+            }
+            String::from_utf8(writer.into_inner()?)?
+                .trim_end()
+                .to_string()
+        }
+    })
+}
+
+impl ToOutput for Vec<ShoppingCart> {
+    fn to_output(&self, format: OutputFormat) -> Result<String, Box<dyn std::error::Error>> {
+        match format {
+            OutputFormat::Table => Ok(self
+                .iter()
+                .map(|cart| cart.text.trim())
+                .collect::<Vec<&str>>()
+                .join("\n")),
+            _ => to_structured(self, format),
+        }
+    }
+}
+
+impl ToOutput for Vec<Course> {
+    fn to_output(&self, format: OutputFormat) -> Result<String, Box<dyn std::error::Error>> {
+        match format {
+            OutputFormat::Table => Ok(self.to_table().to_string()),
+            _ => to_structured(self, format),
+        }
+    }
+}
+
+impl ToOutput for Vec<RegistrationResult> {
+    fn to_output(&self, format: OutputFormat) -> Result<String, Box<dyn std::error::Error>> {
+        match format {
+            OutputFormat::Table => Ok(self.to_table().to_string()),
+            _ => to_structured(self, format),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collapses_free_text_course_fields() {
+        let courses = vec![Course {
+            class_number: Some("4321".to_string()),
+            availability: CourseStatus::Open {
+                available: 3,
+                capacity: 120,
+            },
+            description: "CS 170-1\n  Introduction to Computer Science I".to_string(),
+            schedule: "MoWe\n  10:00AM -\t11:15AM".to_string(),
+            room: " MSC\n W201 ".to_string(),
+            instructor: "Ada\n  Lovelace".to_string(),
+            credits: "4.00".to_string(),
+        }];
+        let json = courses.to_output(OutputFormat::Ndjson).unwrap();
+        for field in [
+            r#""description":"CS 170-1 Introduction to Computer Science I""#,
+            r#""schedule":"MoWe 10:00AM - 11:15AM""#,
+            r#""room":"MSC W201""#,
+            r#""instructor":"Ada Lovelace""#,
+        ] {
+            assert!(json.contains(field), "{json}");
+        }
+        let csv = courses.to_output(OutputFormat::Csv).unwrap();
+        assert!(
+            csv.ends_with(
+                "4321,CS 170-1 Introduction to Computer Science I,4.00,open,3,120,,MoWe 10:00AM - 11:15AM,MSC W201,Ada Lovelace"
+            ),
+            "{csv}"
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use chromiumoxide::error::CdpError;
+use std::error::Error;
+use std::fmt;
+
+use crate::elements::{RegistrationResult, RegistrationStatus};
+
+/// Step of the registration flow, used to say where a page element went missing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Login,
+    Duo,
+    Cart,
+    Session,
+    Courses,
+    Select,
+    Validate,
+    Enroll,
+    Confirm,
+    Results,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Phase::Login => write!(f, "login"),
+            Phase::Duo => write!(f, "duo"),
+            Phase::Cart => write!(f, "shopping cart"),
+            Phase::Session => write!(f, "session check"),
+            Phase::Courses => write!(f, "course list"),
+            Phase::Select => write!(f, "course selection"),
+            Phase::Validate => write!(f, "validate"),
+            Phase::Enroll => write!(f, "enroll"),
+            Phase::Confirm => write!(f, "enroll confirmation"),
+            Phase::Results => write!(f, "results"),
+        }
+    }
+}
+
+/// Everything that can stop a run, each with its own process exit code
+#[derive(Debug)]
+pub enum SniperError {
+    /// Missing or bad inputs from flags, the environment, or a plan
+    Input(String),
+    /// The portal rejected the username or password
+    Auth,
+    /// The MFA prompt was not approved in time
+    DuoTimeout,
+    /// The MFA prompt turned down the passcode and no other was given
+    DuoRejected,
+    /// None of the expected elements showed up in time
+    SelectorNotFound {
+        selector: String,
+        phase: Phase,
+    },
+    /// No shopping cart, or more than one, matches the term
+    CartMissing(String),
+    /// A course is not in the cart, or a pattern matches more than one
+    CourseMissing(String),
+    /// Enrollment went through but no course was enrolled in
+    EnrollmentRejected,
+    /// Only some of the courses were enrolled in
+    PartialEnrollment {
+        enrolled: usize,
+        wanted: usize,
+    },
+    /// Every concurrent sniper failed before getting results
+    SnipersFailed,
+    /// The portal went back to the login or timeout page and logging in again did not help
+    SessionExpired(Phase),
+    Browser(Box<CdpError>),
+}
+
+impl SniperError {
+    /// Process exit code, 1 is left for errors from outside the registration flow
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            SniperError::Input(_) => 2,
+            SniperError::Auth => 3,
+            SniperError::DuoTimeout => 4,
+            SniperError::SelectorNotFound { .. } => 5,
+            SniperError::CartMissing(_) => 6,
+            SniperError::CourseMissing(_) => 7,
+            SniperError::EnrollmentRejected => 8,
+            SniperError::PartialEnrollment { .. } => 9,
+            SniperError::SnipersFailed => 10,
+            SniperError::Browser(_) => 11,
+            SniperError::SessionExpired(_) => 12,
+            SniperError::DuoRejected => 13,
+        }
+    }
+
+    /// Exit code for any error a run can end with
+    pub fn exit_code_of(error: &(dyn Error + 'static)) -> u8 {
+        if let Some(error) = error.downcast_ref::<SniperError>() {
+            error.exit_code()
+        } else if error.is::<CdpError>() {
+            SniperError::Browser(Box::new(CdpError::NotFound)).exit_code()
+        } else {
+            1
+        }
+    }
+
+    /// Errors unless `wanted` courses came back enrolled
+    pub fn check_enrollment(results: &[RegistrationResult], wanted: usize) -> Result<(), Self> {
+        let enrolled = results
+            .iter()
+            .filter(|result| matches!(result.status, RegistrationStatus::Success))
+            .count();
+        if enrolled >= wanted {
+            Ok(())
+        } else if enrolled == 0 {
+            Err(SniperError::EnrollmentRejected)
+        } else {
+            Err(SniperError::PartialEnrollment { enrolled, wanted })
+        }
+    }
+}
+
+impl fmt::Display for SniperError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SniperError::Input(message)
+            | SniperError::CartMissing(message)
+            | SniperError::CourseMissing(message) => write!(f, "{message}"),
+            SniperError::Auth => write!(f, "invalid credentials"),
+            SniperError::DuoTimeout => write!(f, "duo authentication timed out"),
+            SniperError::DuoRejected => write!(f, "duo rejected the passcode"),
+            SniperError::SelectorNotFound { selector, phase } => {
+                write!(f, "timed out waiting for {selector} during {phase}")
+            }
+            SniperError::EnrollmentRejected => write!(f, "no courses were enrolled in"),
+            SniperError::PartialEnrollment { enrolled, wanted } => {
+                write!(f, "enrolled in {enrolled} of {wanted} courses")
+            }
+            SniperError::SnipersFailed => write!(f, "no sniper got enrollment results"),
+            SniperError::SessionExpired(phase) => {
+                write!(f, "the portal logged out during {phase}")
+            }
+            SniperError::Browser(e) => write!(f, "browser error: {e}"),
+        }
+    }
+}
+
+impl Error for SniperError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SniperError::Browser(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<CdpError> for SniperError {
+    fn from(e: CdpError) -> Self {
+        SniperError::Browser(Box::new(e))
+    }
+}
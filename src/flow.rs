@@ -0,0 +1,1139 @@
+use async_std::task::sleep;
+use chromiumoxide::error::CdpError;
+use chromiumoxide::{Browser, Page};
+use chrono::{DateTime, Local, TimeDelta};
+use comfy_table::{Attribute, Cell, Table};
+use inquire::Text;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::block::ResourceBlocker;
+use crate::clock::ServerClock;
+use crate::elements::{
+    Course, CourseStatus, OutputFormat, RegistrationResult, RegistrationStatus, ShoppingCart,
+    ToTable,
+};
+use crate::error::{Phase, SniperError};
+use crate::get_progress_bar;
+use crate::schedule::{countdown, wait_until};
+use crate::school::{
+    AuthTransition, CartTransition, DuoTransition, SchoolPortal, SessionTransition,
+};
+use crate::session::Session;
+
+/// Picks the one cart whose name contains `term`, erroring if none or several do
+pub fn match_cart(mut carts: Vec<ShoppingCart>, term: &str) -> Result<ShoppingCart, SniperError> {
+    let names: Vec<&str> = carts.iter().map(|cart| cart.text.as_str()).collect();
+    let index = match_term(&names, term)?;
+    Ok(carts.swap_remove(index))
+}
+
+/// Index of the one cart name containing `term`, ignoring case
+fn match_term(names: &[&str], term: &str) -> Result<usize, SniperError> {
+    let found = names
+        .iter()
+        .map(|name| name.trim())
+        .collect::<Vec<&str>>()
+        .join(", ");
+    let mut matches = names
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| name.to_lowercase().contains(&term.to_lowercase()))
+        .map(|(index, _)| index);
+    match (matches.next(), matches.next()) {
+        (Some(index), None) => Ok(index),
+        (None, _) => Err(SniperError::CartMissing(format!(
+            "no shopping cart matches \"{term}\", found: {found}"
+        ))),
+        (Some(_), Some(_)) => Err(SniperError::CartMissing(format!(
+            "\"{term}\" matches more than one shopping cart, found: {found}"
+        ))),
+    }
+}
+
+/// Resolves a pattern to exactly one course in the cart
+pub fn match_course(courses: &[Course], pattern: &str) -> Result<Course, SniperError> {
+    let mut matches = courses.iter().filter(|course| course.matches(pattern));
+    match (matches.next(), matches.next()) {
+        (Some(course), None) => Ok(course.clone()),
+        (None, _) => Err(SniperError::CourseMissing(format!(
+            "no course in the cart matches \"{pattern}\""
+        ))),
+        (Some(_), Some(_)) => Err(SniperError::CourseMissing(format!(
+            "\"{pattern}\" matches more than one course in the cart"
+        ))),
+    }
+}
+
+/// Resolves every pattern to exactly one course in the cart
+pub fn match_courses(courses: &[Course], patterns: &[String]) -> Result<Vec<Course>, SniperError> {
+    patterns
+        .iter()
+        .map(|pattern| match_course(courses, pattern))
+        .collect()
+}
+
+/// How often `wait_for_login` looks for a login made by hand
+const LOGIN_POLL: Duration = Duration::from_secs(1);
+
+/// Asks for a Duo passcode, `None` when nobody is at the terminal to type one
+fn prompt_passcode() -> Option<String> {
+    if !std::io::stdin().is_terminal() {
+        return None;
+    }
+    Text::new("Duo passcode:")
+        .with_help_message("From Duo Mobile or a hardware token")
+        .prompt()
+        .ok()
+        .map(|passcode| passcode.trim().to_string())
+        .filter(|passcode| !passcode.is_empty())
+}
+
+/// Wall clock time `elapsed` after `start`
+fn after(start: DateTime<Local>, elapsed: Duration) -> DateTime<Local> {
+    start + TimeDelta::from_std(elapsed).unwrap_or_default()
+}
+
+/// A course to enroll in and the alternates to try, in order, if it fails
+pub struct Target {
+    pub course: Course,
+    pub fallbacks: Vec<Course>,
+}
+
+/// Drives one portal page through the registration phases
+#[derive(Clone, Copy)]
+pub struct Sniper<'a> {
+    pub browser: &'a Browser,
+    pub page: &'a Page,
+    pub school: &'a dyn SchoolPortal,
+    /// Seconds to wait on each page transition
+    pub timeout: u64,
+    /// Seconds to wait for MFA to be approved
+    pub mfa_timeout: u64,
+    /// Pages that reload and enroll at registration time, including this one
+    pub snipers: u8,
+    /// Milliseconds between each sniper's firing time
+    pub stagger: u64,
+    /// Portal clock estimate to fire by, `None` fires on the local clock
+    pub clock: Option<&'a ServerClock>,
+    /// Selects and enrolls with one script in the page instead of clicking each element
+    pub fast_enroll: bool,
+    /// Drops non-essential resources while enrolling, `None` loads everything
+    pub blocker: Option<&'a ResourceBlocker>,
+    /// Seconds between keepalive reloads while waiting for registration, `None` turns them off
+    pub keepalive: Option<u64>,
+    /// Credentials to log back in with once the session expires
+    pub session: &'a Session,
+    /// Times a timed out Duo push is sent again
+    pub duo_retries: u32,
+    /// Passcode to enter instead of waiting on a Duo push
+    pub duo_passcode: Option<&'a str>,
+    /// Runs on a saved browser profile or session file, which may still be logged in
+    pub saved_session: bool,
+    /// Format results are printed in, progress notes stay off stdout unless it is a table
+    pub output: OutputFormat,
+    /// Reads the cart both ways whenever its courses are listed and prints how long each took
+    pub debug: bool,
+}
+
+/// Timings from one sniper's shot at the registration window
+pub struct SniperReport {
+    pub id: usize,
+    /// Milliseconds from the registration time this sniper fired at
+    pub offset: i64,
+    pub reloaded: Option<DateTime<Local>>,
+    pub loaded: Option<DateTime<Local>>,
+    pub enroll_clicked: Option<DateTime<Local>>,
+    /// When confirm was clicked, or with --fast-enroll when the click was queued
+    pub confirm_clicked: Option<DateTime<Local>>,
+    pub finished: Option<DateTime<Local>>,
+    pub outcome: String,
+}
+
+/// Outcome of a sniper that found the portal logged out, it fires again after logging back in
+const LOGGED_OUT: &str = "logged out";
+
+impl SniperReport {
+    fn new(id: usize, offset: i64) -> Self {
+        Self {
+            id,
+            offset,
+            reloaded: None,
+            loaded: None,
+            enroll_clicked: None,
+            confirm_clicked: None,
+            finished: None,
+            outcome: String::new(),
+        }
+    }
+}
+
+impl ToTable for Vec<SniperReport> {
+    fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        table.set_header(vec![
+            Cell::new("Sniper"),
+            Cell::new("Offset"),
+            Cell::new("Reloaded"),
+            Cell::new("Loaded"),
+            Cell::new("Enroll"),
+            Cell::new("Confirm"),
+            Cell::new("Results"),
+            Cell::new("Outcome").add_attribute(Attribute::Bold),
+        ]);
+
+        let time = |time: &Option<DateTime<Local>>| {
+            Cell::new(
+                time.map(|time| time.format("%H:%M:%S.%3f").to_string())
+                    .unwrap_or("-".to_string()),
+            )
+        };
+        for report in self {
+            table.add_row(vec![
+                Cell::new(report.id),
+                Cell::new(format!("{:+}ms", report.offset)),
+                time(&report.reloaded),
+                time(&report.loaded),
+                time(&report.enroll_clicked),
+                time(&report.confirm_clicked),
+                time(&report.finished),
+                Cell::new(report.outcome.clone()),
+            ]);
+        }
+        table
+    }
+}
+
+impl Sniper<'_> {
+    /// Prints a progress note, on stderr when stdout is for `--output`
+    pub fn note(&self, message: impl std::fmt::Display) {
+        match self.output {
+            OutputFormat::Table => println!("{message}"),
+            _ => eprintln!("{message}"),
+        }
+    }
+
+    /// Logs in and waits through Duo if the portal asks for it, unless the saved profile is
+    /// still logged in
+    pub async fn login(&self, username: &str, password: &str) -> Result<(), SniperError> {
+        if self.saved_session && self.logged_in().await? {
+            // kept for logging back in once the saved session runs out
+            self.session.remember(username, password);
+            return Ok(());
+        }
+
+        let pb = get_progress_bar("Logging in with credentials...");
+
+        // login and authentication transition
+        let login = async {
+            self.school
+                .submit_login(self.page, username, password, self.timeout)
+                .await?;
+            self.school
+                .login_transition()
+                .race(self.page, self.timeout)
+                .await
+        };
+        let matched = match login.await {
+            Ok(matched) => matched,
+            Err(e) => {
+                pb.finish_with_message("Failed to find the correct elements or timed out.");
+                Err(e)?
+            }
+        };
+        match matched.outcome {
+            AuthTransition::AuthSuccess => {
+                pb.finish_with_message(format!("Authenticated in {:.2?}.", matched.elapsed))
+            }
+            AuthTransition::AuthFail => {
+                pb.finish_with_message("Invalid credentials.");
+                Err(SniperError::Auth)?
+            }
+            AuthTransition::Duo => {
+                pb.finish_with_message("Duo authentication required.");
+                self.pass_duo().await?;
+            }
+        }
+        self.session.remember(username, password);
+        Ok(())
+    }
+
+    /// Waits on the Duo push, sending it again up to `duo_retries` times as it times out, then
+    /// falls back to a passcode from `--duo-passcode` or the terminal
+    async fn pass_duo(&self) -> Result<(), SniperError> {
+        // a passcode given up front skips waiting on the push
+        let mut passcode = self.duo_passcode.map(str::to_string);
+        if passcode.is_none() {
+            let mut sent = 1;
+            loop {
+                let pb = get_progress_bar("Waiting for Duo confirmation...");
+                let matched = match self
+                    .school
+                    .mfa_transition()
+                    .race(self.page, self.mfa_timeout)
+                    .await
+                {
+                    Ok(matched) => matched,
+                    Err(e) => {
+                        pb.finish_with_message("Failed to find the correct elements or timed out.");
+                        Err(e)?
+                    }
+                };
+                match matched.outcome {
+                    DuoTransition::Trust | DuoTransition::Cart => {
+                        pb.finish_with_message(format!(
+                            "Authenticated, Duo push {sent} approved in {:.2?}.",
+                            matched.elapsed
+                        ));
+                        return Ok(());
+                    }
+                    DuoTransition::TimeOut if sent <= self.duo_retries => {
+                        pb.finish_with_message(format!(
+                            "Duo push {sent} timed out, sending it again."
+                        ));
+                        self.school.resend_push(self.page, self.timeout).await?;
+                        sent += 1;
+                    }
+                    DuoTransition::TimeOut | DuoTransition::PasscodeRejected => {
+                        pb.finish_with_message("Duo authentication timed out.");
+                        break;
+                    }
+                }
+            }
+            passcode = prompt_passcode();
+        }
+
+        let mut passcode = passcode.ok_or(SniperError::DuoTimeout)?;
+        loop {
+            let pb = get_progress_bar("Entering Duo passcode...");
+            let entered = async {
+                self.school
+                    .submit_passcode(self.page, &passcode, self.timeout)
+                    .await?;
+                self.school
+                    .passcode_transition()
+                    .race(self.page, self.timeout)
+                    .await
+            };
+            let matched = match entered.await {
+                Ok(matched) => matched,
+                Err(e) => {
+                    pb.finish_with_message("Failed to find the correct elements or timed out.");
+                    Err(e)?
+                }
+            };
+            match matched.outcome {
+                DuoTransition::Trust | DuoTransition::Cart => {
+                    pb.finish_with_message(format!(
+                        "Authenticated, Duo passcode accepted in {:.2?}.",
+                        matched.elapsed
+                    ));
+                    return Ok(());
+                }
+                DuoTransition::PasscodeRejected => {
+                    pb.finish_with_message("Duo rejected the passcode.");
+                    passcode = prompt_passcode().ok_or(SniperError::DuoRejected)?;
+                }
+                DuoTransition::TimeOut => {
+                    pb.finish_with_message("Duo authentication timed out.");
+                    Err(SniperError::DuoTimeout)?
+                }
+            }
+        }
+    }
+
+    /// Whether the portal opened logged in, e.g. from a session saved in the browser profile
+    pub async fn logged_in(&self) -> Result<bool, SniperError> {
+        let pb = get_progress_bar("Checking for a saved session...");
+        let matched = match self
+            .school
+            .session_transition()
+            .race(self.page, self.timeout)
+            .await
+        {
+            Ok(matched) => matched,
+            Err(e) => {
+                pb.finish_with_message("Failed to find the correct elements or timed out.");
+                Err(e)?
+            }
+        };
+        match matched.outcome {
+            SessionTransition::Alive => {
+                pb.finish_with_message(format!(
+                    "Saved session is logged in, checked in {:.2?}.",
+                    matched.elapsed
+                ));
+                Ok(true)
+            }
+            SessionTransition::Expired => {
+                pb.finish_with_message("No saved session, logging in.");
+                Ok(false)
+            }
+        }
+    }
+
+    /// Waits up to `wait_time` seconds for someone to log in by hand in the attached browser
+    pub async fn wait_for_login(&self, wait_time: u64) -> Result<(), SniperError> {
+        let pb = get_progress_bar("Waiting for a login in the browser window...");
+        let deadline = Instant::now() + Duration::from_secs(wait_time);
+        while Instant::now() < deadline {
+            if let Ok(matched) = self
+                .school
+                .session_transition()
+                .race(self.page, self.timeout)
+                .await
+            {
+                if let SessionTransition::Alive = matched.outcome {
+                    pb.finish_with_message("Logged in.");
+                    return Ok(());
+                }
+            }
+            sleep(LOGIN_POLL).await;
+        }
+        pb.finish_with_message("Nobody logged in.");
+        Err(SniperError::Input(format!(
+            "nobody logged in within {wait_time} seconds, log in with --attach or pass --user"
+        )))
+    }
+
+    /// Logs in again on `page`, which is showing the login form, with the last credentials
+    pub async fn relogin(&self, page: &Page) -> Result<(), SniperError> {
+        let credentials = self.session.credentials().ok_or(SniperError::Auth)?;
+        Sniper { page, ..*self }
+            .login(&credentials.username, &credentials.password)
+            .await
+    }
+
+    /// Opens the portal on `page`, logging in again if the session has expired
+    pub async fn resume_session(&self, page: &Page) -> Result<(), SniperError> {
+        page.goto(self.school.page_url()).await?;
+        let matched = self
+            .school
+            .session_transition()
+            .race(page, self.timeout)
+            .await?;
+        if let SessionTransition::Expired = matched.outcome {
+            self.relogin(page).await?;
+        }
+        Ok(())
+    }
+
+    /// Runs `phase`, and if the portal logs out partway through, logs back in, reopens the cart
+    /// and runs it once more from the start
+    async fn resuming<T, F, Fut>(&self, phase: F) -> Result<T, SniperError>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, SniperError>>,
+    {
+        match phase().await {
+            Err(SniperError::SessionExpired(interrupted)) => {
+                self.note(format!("Logged out during {interrupted}, logging back in."));
+                self.resume_session(self.page).await?;
+                self.enter_cart(self.session.term().as_deref()).await?;
+                phase().await
+            }
+            result => result,
+        }
+    }
+
+    /// Waits for the cart page, returns `None` when the portal went straight into the only cart
+    pub async fn find_carts(&self) -> Result<Option<Vec<ShoppingCart>>, SniperError> {
+        let pb = get_progress_bar("Looking for shopping cart...");
+        match self
+            .school
+            .cart_transition()
+            .race(self.page, self.timeout)
+            .await
+        {
+            Ok(matched) => match matched.outcome {
+                CartTransition::In => {
+                    pb.finish_with_message("Entered shopping cart.");
+                    Ok(None)
+                }
+                CartTransition::Select => {
+                    pb.finish_with_message("Shopping carts found.");
+                    Ok(Some(self.school.get_shopping_carts(self.page).await?))
+                }
+                CartTransition::Expired => {
+                    pb.finish_with_message("Logged out before the cart loaded.");
+                    Err(SniperError::SessionExpired(Phase::Cart))
+                }
+            },
+            Err(e) => {
+                pb.finish_with_message("Failed to find the correct elements or timed out.");
+                Err(e)?
+            }
+        }
+    }
+
+    /// Opens the cart matching `term`, only guessing when the account has a single cart
+    pub async fn open_cart(&self, term: Option<&str>) -> Result<(), SniperError> {
+        self.session.remember_term(term);
+        self.resuming(|| self.enter_cart(term)).await
+    }
+
+    async fn enter_cart(&self, term: Option<&str>) -> Result<(), SniperError> {
+        let Some(mut carts) = self.find_carts().await? else {
+            return Ok(());
+        };
+        let selected_cart = match term {
+            Some(term) => match_cart(carts, term)?,
+            None if carts.len() == 1 => carts.remove(0),
+            None => Err(SniperError::CartMissing(
+                "the account has several shopping carts, pick one by term".to_string(),
+            ))?,
+        };
+        selected_cart.element.click().await?;
+        Ok(())
+    }
+
+    pub async fn get_courses(&self) -> Result<Vec<Course>, SniperError> {
+        let pb = get_progress_bar("Fetching courses in cart...");
+        let cart = self
+            .resuming(|| self.school.get_cart_courses(self.page, self.timeout))
+            .await?;
+        pb.finish_with_message(format!(
+            "Found {} courses in {:.2?}, read {}.",
+            cart.courses.len(),
+            cart.elapsed,
+            cart.scraper
+        ));
+        if self.debug {
+            let (script, elements) = self.school.time_cart_scrapers(self.page).await?;
+            let script = match script {
+                Some(elapsed) => format!("{elapsed:.2?}"),
+                None => "an incomplete read".to_string(),
+            };
+            self.note(format!(
+                "Reading the cart took {script} with one script and {elements:.2?} element by element."
+            ));
+        }
+        Ok(cart.courses)
+    }
+
+    /// Probes the portal's clock ahead of `registration_time`, unless firing on the local clock
+    async fn sync_clock(&self, registration_time: DateTime<Local>) {
+        if let Some(clock) = self.clock {
+            clock.sync(self.page, registration_time).await;
+        }
+    }
+
+    /// Local time to reload at so the request reaches the portal just after `registration_time`
+    fn fire_time(&self, registration_time: DateTime<Local>) -> DateTime<Local> {
+        match self.clock {
+            Some(clock) => clock.fire_time(registration_time),
+            None => registration_time,
+        }
+    }
+
+    /// Waits for `registration_time`, reloads the cart, and enrolls in `courses`
+    pub async fn enroll(
+        &self,
+        courses: &[Course],
+        registration_time: DateTime<Local>,
+    ) -> Result<Vec<RegistrationResult>, SniperError> {
+        if self.snipers > 1 {
+            return self.enroll_concurrently(courses, registration_time).await;
+        }
+
+        let pb = get_progress_bar("Waiting for registration time...");
+        futures::join!(
+            countdown(registration_time, &pb, self.clock),
+            self.keep_alive(registration_time, &pb),
+            async {
+                self.sync_clock(registration_time).await;
+                wait_until(self.fire_time(registration_time)).await
+            }
+        );
+        pb.finish_with_message(format!(
+            "Reloaded for registration at {}.",
+            Local::now().format("%H:%M:%S.%3f")
+        ));
+
+        self.page.reload().await?.wait_for_navigation().await?;
+
+        self.note(format!(
+            "Page finished loading at {}",
+            Local::now().format("%H:%M:%S.%3f")
+        ));
+        self.select_and_enroll(courses).await
+    }
+
+    /// Opens the extra snipers on the current cart and has every one reload and enroll,
+    /// staggered around `registration_time`
+    async fn enroll_concurrently(
+        &self,
+        courses: &[Course],
+        registration_time: DateTime<Local>,
+    ) -> Result<Vec<RegistrationResult>, SniperError> {
+        let pb = get_progress_bar(format!("Opening {} snipers...", self.snipers));
+        let url = self.page.url().await?.ok_or(CdpError::NotFound)?;
+        let opened = futures::future::join_all((1..self.snipers).map(|_| async {
+            let page = self.browser.new_page(url.as_str()).await?;
+            // a page that fails to set up is still closed with the rest
+            let setup = async {
+                page.enable_stealth_mode().await?;
+                if let Some(blocker) = self.blocker {
+                    blocker.block(&page).await?;
+                }
+                Ok::<(), CdpError>(())
+            }
+            .await;
+            Ok::<(Page, Result<(), CdpError>), CdpError>((page, setup))
+        }))
+        .await;
+        let mut extra_pages = Vec::new();
+        let mut failed = None;
+        for result in opened {
+            match result {
+                Ok((page, setup)) => {
+                    extra_pages.push(page);
+                    failed = failed.or(setup.err());
+                }
+                Err(e) => failed = failed.or(Some(e)),
+            }
+        }
+        if let Some(e) = failed {
+            pb.finish_and_clear();
+            self.close_pages(extra_pages).await;
+            return Err(e.into());
+        }
+        pb.finish_with_message(format!("{} snipers ready.", self.snipers));
+
+        let pb = get_progress_bar("Waiting for registration time...");
+        let won = AtomicBool::new(false);
+        let first_offset = -((self.snipers as i64 - 1) * self.stagger as i64) / 2;
+        let pages: Vec<&Page> = std::iter::once(self.page)
+            .chain(extra_pages.iter())
+            .collect();
+        let (_, _, mut shots) = futures::join!(
+            countdown(registration_time, &pb, self.clock),
+            self.keep_alive(registration_time, &pb),
+            async {
+                self.sync_clock(registration_time).await;
+                futures::future::join_all(pages.iter().copied().enumerate().map(|(id, page)| {
+                    let sniper = Sniper { page, ..*self };
+                    let offset = first_offset + id as i64 * self.stagger as i64;
+                    let won = &won;
+                    async move {
+                        sniper
+                            .fire(id, courses, registration_time, offset, won)
+                            .await
+                    }
+                }))
+                .await
+            }
+        );
+        pb.finish_with_message("Snipers finished.");
+
+        let refired = self.refire(&pages, &mut shots, courses, &won).await;
+        self.close_pages(extra_pages).await;
+        refired?;
+
+        let (reports, results): (Vec<SniperReport>, Vec<_>) = shots.into_iter().unzip();
+        self.note(reports.to_table());
+
+        // the winner if there is one, otherwise whoever heard back from the portal last
+        let winner = reports.iter().position(|report| report.outcome == "won");
+        let latest = reports
+            .iter()
+            .enumerate()
+            .filter(|(id, _)| results[*id].is_some())
+            .max_by_key(|(_, report)| report.finished)
+            .map(|(id, _)| id);
+        match winner.or(latest) {
+            Some(id) => Ok(results.into_iter().nth(id).flatten().unwrap_or_default()),
+            None => Err(SniperError::SnipersFailed),
+        }
+    }
+
+    /// Logs back in once when snipers were logged out and none won, then has each of those
+    /// snipers reopen the cart and fire again straight away
+    async fn refire(
+        &self,
+        pages: &[&Page],
+        shots: &mut [(SniperReport, Option<Vec<RegistrationResult>>)],
+        courses: &[Course],
+        won: &AtomicBool,
+    ) -> Result<(), SniperError> {
+        let logged_out: Vec<usize> = shots
+            .iter()
+            .filter(|(report, _)| report.outcome == LOGGED_OUT)
+            .map(|(report, _)| report.id)
+            .collect();
+        if logged_out.is_empty() || won.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        self.note(format!(
+            "{} snipers were logged out during {}, logging back in.",
+            logged_out.len(),
+            Phase::Enroll
+        ));
+        // the snipers share the browser's cookies, so one login does for all of them
+        self.resume_session(self.page).await?;
+        let retries = futures::future::join_all(logged_out.iter().map(|&id| {
+            let sniper = Sniper {
+                page: pages[id],
+                ..*self
+            };
+            async move {
+                let reopened = async {
+                    sniper.resume_session(sniper.page).await?;
+                    sniper.enter_cart(sniper.session.term().as_deref()).await?;
+                    sniper
+                        .school
+                        .get_cart_courses(sniper.page, sniper.timeout)
+                        .await
+                }
+                .await;
+                match reopened {
+                    Ok(_) => sniper.fire(id, courses, Local::now(), 0, won).await,
+                    Err(e) => {
+                        let mut report = SniperReport::new(id, 0);
+                        report.outcome = format!("error: {e}");
+                        (report, None)
+                    }
+                }
+            }
+        }))
+        .await;
+        for shot in retries {
+            let id = shot.0.id;
+            shots[id] = shot;
+        }
+        Ok(())
+    }
+
+    /// Closes the extra sniper pages, a page that will not close is only worth a note
+    async fn close_pages(&self, pages: Vec<Page>) {
+        for page in pages {
+            if let Err(e) = page.close().await {
+                self.note(format!("Could not close a sniper page: {e}"));
+            }
+        }
+    }
+
+    /// One sniper's reload and enroll, standing down once another sniper has succeeded
+    async fn fire(
+        &self,
+        id: usize,
+        courses: &[Course],
+        registration_time: DateTime<Local>,
+        offset: i64,
+        won: &AtomicBool,
+    ) -> (SniperReport, Option<Vec<RegistrationResult>>) {
+        let mut report = SniperReport::new(id, offset);
+        wait_until(self.fire_time(registration_time) + chrono::Duration::milliseconds(offset))
+            .await;
+
+        let shot = async {
+            report.reloaded = Some(Local::now());
+            self.page.reload().await?.wait_for_navigation().await?;
+            report.loaded = Some(Local::now());
+            if self.fast_enroll {
+                if won.load(Ordering::SeqCst) {
+                    return Ok(None);
+                }
+                let started = Local::now();
+                let timings = self
+                    .school
+                    .scripted_enroll(self.page, courses, self.timeout)
+                    .await?;
+                report.enroll_clicked = Some(after(started, timings.enroll_clicked));
+                report.confirm_clicked = Some(after(started, timings.confirm_queued));
+            } else {
+                self.school
+                    .select_courses(self.page, courses, self.timeout)
+                    .await?;
+                if won.load(Ordering::SeqCst) {
+                    return Ok(None);
+                }
+                self.school.enroll(self.page, self.timeout).await?;
+                report.enroll_clicked = Some(Local::now());
+                if won.load(Ordering::SeqCst) {
+                    return Ok(None);
+                }
+                self.school.confirm_enroll(self.page, self.timeout).await?;
+                report.confirm_clicked = Some(Local::now());
+            }
+            let results = self
+                .school
+                .get_registration_results(self.page, self.timeout)
+                .await?;
+            report.finished = Some(Local::now());
+            Ok::<_, SniperError>(Some(results))
+        }
+        .await;
+
+        match shot {
+            Ok(Some(results)) => {
+                let succeeded = results
+                    .iter()
+                    .any(|result| matches!(result.status, RegistrationStatus::Success));
+                report.outcome = if !succeeded {
+                    "no success".to_string()
+                } else if won.swap(true, Ordering::SeqCst) {
+                    "beaten".to_string()
+                } else {
+                    "won".to_string()
+                };
+                (report, Some(results))
+            }
+            Ok(None) => {
+                report.outcome = "stood down".to_string();
+                (report, None)
+            }
+            Err(SniperError::SessionExpired(_)) => {
+                report.outcome = LOGGED_OUT.to_string();
+                (report, None)
+            }
+            Err(e) => {
+                report.outcome = format!("error: {e}");
+                (report, None)
+            }
+        }
+    }
+
+    /// Enrolls in every target, moving on to each target's fallbacks while it keeps failing
+    pub async fn enroll_with_fallbacks(
+        &self,
+        targets: Vec<Target>,
+        registration_time: DateTime<Local>,
+    ) -> Result<Vec<RegistrationResult>, SniperError> {
+        self.blocking(self.enroll_in_rounds(targets, registration_time))
+            .await
+    }
+
+    async fn enroll_in_rounds(
+        &self,
+        targets: Vec<Target>,
+        registration_time: DateTime<Local>,
+    ) -> Result<Vec<RegistrationResult>, SniperError> {
+        let mut attempts: Vec<(Course, VecDeque<Course>)> = targets
+            .into_iter()
+            .map(|target| (target.course, target.fallbacks.into()))
+            .collect();
+        let courses: Vec<Course> = attempts.iter().map(|(course, _)| course.clone()).collect();
+        let mut round_results = self.enroll(&courses, registration_time).await?;
+        let mut registration_results = Vec::new();
+
+        loop {
+            let mut retries = Vec::new();
+            for (current, fallbacks) in attempts.iter_mut() {
+                let failed = round_results.iter().any(|result| {
+                    result.is_for(current) && matches!(result.status, RegistrationStatus::Fail)
+                });
+                if failed {
+                    if let Some(fallback) = fallbacks.pop_front() {
+                        self.note(format!("{current} failed, falling back to {fallback}."));
+                        *current = fallback.clone();
+                        retries.push(fallback);
+                    }
+                }
+            }
+            registration_results.append(&mut round_results);
+            if retries.is_empty() {
+                break;
+            }
+
+            // the cart changes after an enrollment so find the fallbacks again
+            self.page.reload().await?.wait_for_navigation().await?;
+            let courses = self
+                .resuming(|| self.school.get_cart_courses(self.page, self.timeout))
+                .await?
+                .courses;
+            let retries: Vec<Course> = retries
+                .iter()
+                .filter_map(|retry| {
+                    let found = courses.iter().find(|course| course.same_class(retry));
+                    if found.is_none() {
+                        self.note(format!(
+                            "{retry} is no longer in the cart, skipping the fallback."
+                        ));
+                    }
+                    found.cloned()
+                })
+                .collect();
+            if retries.is_empty() {
+                break;
+            }
+            round_results = self.select_and_enroll(&retries).await?;
+        }
+        Ok(registration_results)
+    }
+
+    /// Re-reads the cart every `interval` seconds and enrolls in watched courses once they
+    /// have open seats, until all are enrolled or `max_attempts` or `max_polls` run out
+    pub async fn watch(
+        &self,
+        watched: Vec<Course>,
+        interval: u64,
+        max_attempts: u32,
+        max_polls: Option<u32>,
+    ) -> Result<Vec<RegistrationResult>, SniperError> {
+        self.blocking(self.poll_and_enroll(watched, interval, max_attempts, max_polls))
+            .await
+    }
+
+    async fn poll_and_enroll(
+        &self,
+        mut watched: Vec<Course>,
+        interval: u64,
+        max_attempts: u32,
+        max_polls: Option<u32>,
+    ) -> Result<Vec<RegistrationResult>, SniperError> {
+        let mut registration_results = Vec::new();
+        let mut attempts = 0;
+        let mut polls = 0;
+
+        loop {
+            polls += 1;
+            let pb = get_progress_bar(format!("Checking {} watched courses...", watched.len()));
+            let courses = self
+                .resuming(|| self.school.get_cart_courses(self.page, self.timeout))
+                .await?
+                .courses;
+
+            // a course leaves the cart once enrolled, so stop watching anything no longer there
+            watched.retain(|watching| {
+                let in_cart = courses.iter().any(|course| course.same_class(watching));
+                if !in_cart {
+                    self.note(format!(
+                        "{watching} is no longer in the cart, no longer watching it."
+                    ));
+                }
+                in_cart
+            });
+            let open: Vec<Course> = courses
+                .into_iter()
+                .filter(|course| watched.iter().any(|watching| watching.same_class(course)))
+                .filter(|course| match course.availability {
+                    CourseStatus::Open { available, .. } => available > 0,
+                    _ => false,
+                })
+                .collect();
+
+            if open.is_empty() {
+                pb.finish_with_message(format!(
+                    "No open seats at {} (check {polls}).",
+                    Local::now().format("%H:%M:%S")
+                ));
+            } else {
+                pb.finish_with_message(format!(
+                    "Seats opened in {}.",
+                    open.iter()
+                        .map(|course| course.to_string())
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ));
+                attempts += 1;
+                let mut round_results = self.select_and_enroll(&open).await?;
+                watched.retain(|watching| {
+                    !round_results.iter().any(|result| {
+                        result.is_for(watching)
+                            && matches!(result.status, RegistrationStatus::Success)
+                    })
+                });
+                registration_results.append(&mut round_results);
+            }
+
+            if watched.is_empty() {
+                break;
+            }
+            if attempts >= max_attempts {
+                self.note(format!("Giving up after {attempts} enrollment attempts."));
+                break;
+            }
+            if max_polls.is_some_and(|max_polls| polls >= max_polls) {
+                self.note(format!("Giving up after {polls} cart checks."));
+                break;
+            }
+
+            sleep(Duration::from_secs(interval)).await;
+            self.page.reload().await?.wait_for_navigation().await?;
+        }
+        Ok(registration_results)
+    }
+
+    /// Runs `phase` with non-essential resources blocked on this sniper's page
+    async fn blocking<T>(
+        &self,
+        phase: impl Future<Output = Result<T, SniperError>>,
+    ) -> Result<T, SniperError> {
+        let Some(blocker) = self.blocker else {
+            return phase.await;
+        };
+        blocker.block(self.page).await?;
+        let result = phase.await;
+        let unblocked = blocker.unblock(self.page).await;
+        self.note(format!(
+            "Blocked {} requests while enrolling.",
+            blocker.blocked()
+        ));
+        let result = result?;
+        unblocked?;
+        Ok(result)
+    }
+
+    /// Selects `courses`, clicks enroll and confirm, and reads back the results. Logging out
+    /// before the confirmation goes through starts over from selecting once logged back in.
+    async fn select_and_enroll(
+        &self,
+        courses: &[Course],
+    ) -> Result<Vec<RegistrationResult>, SniperError> {
+        self.resuming(|| self.submit(courses)).await?;
+
+        // results
+        let pb = get_progress_bar("Waiting for enrollment results...");
+        let registration_results = self
+            .school
+            .get_registration_results(self.page, self.timeout)
+            .await?;
+        pb.finish_with_message(format!(
+            "Found {} enrollment results.",
+            registration_results.len()
+        ));
+        Ok(registration_results)
+    }
+
+    /// Selects `courses` and clicks enroll and confirm, logging when each step finished so the
+    /// click path and `--fast-enroll` can be compared
+    async fn submit(&self, courses: &[Course]) -> Result<(), SniperError> {
+        let started = Local::now();
+        let step = |name: &str, at: DateTime<Local>| {
+            self.note(format!(
+                "{name} at {} (+{}ms)",
+                at.format("%H:%M:%S.%3f"),
+                (at - started).num_milliseconds()
+            ))
+        };
+        if self.fast_enroll {
+            let pb = get_progress_bar("Selecting courses and enrolling in one script...");
+            let timings = self
+                .school
+                .scripted_enroll(self.page, courses, self.timeout)
+                .await?;
+            pb.finish_with_message("Courses selected and enrollment confirmed.");
+            step("Courses selected", after(started, timings.selected));
+            step("Enroll clicked", after(started, timings.enroll_clicked));
+            step("Confirm queued", after(started, timings.confirm_queued));
+        } else {
+            let pb = get_progress_bar("Selecting courses...");
+            self.school
+                .select_courses(self.page, courses, self.timeout)
+                .await?;
+            pb.finish_with_message("Courses selected.");
+            step("Courses selected", Local::now());
+
+            // enroll
+            self.school.enroll(self.page, self.timeout).await?;
+            step("Enroll clicked", Local::now());
+
+            // confirm
+            self.school.confirm_enroll(self.page, self.timeout).await?;
+            step("Confirm clicked", Local::now());
+        }
+        Ok(())
+    }
+
+    pub async fn validate(
+        &self,
+        courses: &[Course],
+    ) -> Result<Vec<RegistrationResult>, SniperError> {
+        self.resuming(|| async {
+            let pb = get_progress_bar("Selecting courses...");
+            self.school
+                .select_courses(self.page, courses, self.timeout)
+                .await?;
+            pb.finish_with_message("Courses selected.");
+
+            // validate
+            self.school.validate(self.page, self.timeout).await
+        })
+        .await?;
+
+        self.note(format!(
+            "Validation clicked at {}",
+            Local::now().format("%H:%M:%S.%3f")
+        ));
+        // results
+        let pb = get_progress_bar("Waiting for validation results...");
+        let registration_results = self
+            .school
+            .get_registration_results(self.page, self.timeout)
+            .await?;
+        pb.finish_with_message(format!(
+            "Found {} validation results.",
+            registration_results.len()
+        ));
+        Ok(registration_results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn course(class_number: Option<&str>, description: &str) -> Course {
+        Course {
+            class_number: class_number.map(str::to_string),
+            availability: CourseStatus::Closed,
+            description: description.to_string(),
+            schedule: String::new(),
+            room: String::new(),
+            instructor: String::new(),
+            credits: String::new(),
+        }
+    }
+
+    fn cart() -> Vec<Course> {
+        vec![
+            course(Some("4321"), "CS 170-1 Introduction to Computer Science I"),
+            course(Some("4322"), "CS 170-2 Introduction to Computer Science I"),
+            course(None, "MATH 221-3 Linear Algebra"),
+        ]
+    }
+
+    #[test]
+    fn matches_one_cart_by_term() {
+        let names = ["Spring 2027", " Summer 2027 ", "Fall 2027"];
+        assert_eq!(match_term(&names, "summer").unwrap(), 1);
+        assert_eq!(match_term(&names, "Fall 2027").unwrap(), 2);
+    }
+
+    #[test]
+    fn rejects_missing_and_ambiguous_terms() {
+        let names = ["Spring 2027", "Summer 2027"];
+        let missing = match_term(&names, "Fall").unwrap_err().to_string();
+        assert!(missing.contains("no shopping cart matches"), "{missing}");
+        assert!(missing.contains("Spring 2027, Summer 2027"), "{missing}");
+        let ambiguous = match_term(&names, "2027").unwrap_err().to_string();
+        assert!(ambiguous.contains("more than one"), "{ambiguous}");
+    }
+
+    #[test]
+    fn matches_courses_by_class_number_or_description() {
+        assert_eq!(
+            match_course(&cart(), "4322").unwrap().description,
+            "CS 170-2 Introduction to Computer Science I"
+        );
+        assert_eq!(
+            match_course(&cart(), "linear").unwrap().description,
+            "MATH 221-3 Linear Algebra"
+        );
+        // class numbers have to match exactly, not as part of the description
+        assert!(match_course(&cart(), "432").is_err());
+    }
+
+    #[test]
+    fn rejects_missing_and_ambiguous_courses() {
+        let ambiguous = match_course(&cart(), "CS 170").unwrap_err().to_string();
+        assert!(ambiguous.contains("more than one"), "{ambiguous}");
+        let missing = match_course(&cart(), "PHYS").unwrap_err().to_string();
+        assert!(missing.contains("no course"), "{missing}");
+        assert!(match_courses(&cart(), &["4321".to_string(), "CS".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use chromiumoxide::cdp::browser_protocol::network::{Cookie, CookieParam, TimeSinceEpoch};
+use chromiumoxide::cdp::browser_protocol::page::GetNavigationHistoryParams;
+use chromiumoxide::{Browser, Page};
+use chrono::{DateTime, Local};
+use inquire::{Password, PasswordDisplayMode};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use zeroize::Zeroizing;
+
+use crate::error::SniperError;
+use crate::seal;
+
+const KEY_ENV: &str = "COURSE_SNIPER_SESSION_KEY";
+
+/// Domain Duo's prompt is served from, usually in a frame that never shows in the history
+const DUO_DOMAIN: &str = "duosecurity.com";
+
+/// Cookies of a logged in portal session as kept in a session file
+#[derive(Serialize, Deserialize)]
+struct SavedSession {
+    /// Portal page the session was saved from
+    portal: String,
+    saved_at: DateTime<Local>,
+    cookies: Vec<CookieParam>,
+}
+
+/// Host of `url` without scheme, port or path
+fn host(url: &str) -> &str {
+    let start = url.find("://").map(|scheme| scheme + 3).unwrap_or(0);
+    let host = url[start..]
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or_default();
+    host.split(':').next().unwrap_or_default()
+}
+
+/// Whether a cookie for `domain` is sent to `host`, i.e. `host` is `domain` or under it
+fn domain_match(host: &str, domain: &str) -> bool {
+    let domain = domain.trim_start_matches('.');
+    host == domain
+        || host
+            .strip_suffix(domain)
+            .is_some_and(|sub| sub.ends_with('.'))
+}
+
+/// Whether a cookie for `domain` is part of the login, i.e. sent to the portal or a single
+/// sign on page in `hosts`, or set by Duo
+fn belongs(domain: &str, hosts: &[&str]) -> bool {
+    hosts.iter().any(|host| domain_match(host, domain))
+        || domain_match(domain.trim_start_matches('.'), DUO_DOMAIN)
+}
+
+/// Hosts `page` went through, the portal and the single sign on pages it logged in on
+async fn visited(page: &Page) -> Result<Vec<String>, SniperError> {
+    let history = page.execute(GetNavigationHistoryParams::default()).await?;
+    Ok(history
+        .result
+        .entries
+        .iter()
+        .map(|entry| host(&entry.url).to_string())
+        .collect())
+}
+
+fn to_param(cookie: Cookie) -> CookieParam {
+    CookieParam {
+        name: cookie.name,
+        value: cookie.value,
+        url: None,
+        domain: Some(cookie.domain),
+        path: Some(cookie.path),
+        secure: Some(cookie.secure),
+        http_only: Some(cookie.http_only),
+        same_site: cookie.same_site,
+        // session cookies have no expiry and go away with the browser that restores them
+        expires: (!cookie.session).then(|| TimeSinceEpoch::new(cookie.expires)),
+        priority: Some(cookie.priority),
+        same_party: None,
+        source_scheme: Some(cookie.source_scheme),
+        source_port: Some(cookie.source_port),
+        partition_key: cookie.partition_key,
+    }
+}
+
+/// Passphrase session files are encrypted with, from the environment or asked for, wiped
+/// once dropped
+pub fn passphrase(confirm: bool) -> Result<Zeroizing<String>, SniperError> {
+    if let Ok(key) = std::env::var(KEY_ENV) {
+        return Ok(Zeroizing::new(key));
+    }
+    let help = format!("Set {KEY_ENV} to run without prompts");
+    let prompt = Password::new("Session file passphrase:")
+        .with_display_mode(PasswordDisplayMode::Masked)
+        .with_help_message(&help);
+    let prompt = if confirm {
+        prompt
+    } else {
+        prompt.without_confirmation()
+    };
+    prompt
+        .prompt()
+        .map(Zeroizing::new)
+        .map_err(|_| SniperError::Input(format!("no session file passphrase, set {KEY_ENV}")))
+}
+
+/// Writes the cookies `browser` holds for the portal, the single sign on pages `page` logged
+/// in through and Duo to `path`, encrypted with `passphrase`, and returns how many there were
+pub async fn save(
+    browser: &Browser,
+    page: &Page,
+    portal: &str,
+    path: &Path,
+    passphrase: &str,
+) -> Result<usize, SniperError> {
+    let visited = visited(page).await?;
+    let hosts: Vec<&str> = std::iter::once(host(portal))
+        .chain(visited.iter().map(String::as_str))
+        .collect();
+    let cookies: Vec<CookieParam> = browser
+        .get_cookies()
+        .await?
+        .into_iter()
+        .filter(|cookie| belongs(&cookie.domain, &hosts))
+        .map(to_param)
+        .collect();
+    let count = cookies.len();
+    let saved = SavedSession {
+        portal: portal.to_string(),
+        saved_at: Local::now(),
+        cookies,
+    };
+    let json = serde_json::to_vec(&saved)
+        .map_err(|e| SniperError::Input(format!("could not write the session: {e}")))?;
+    std::fs::write(path, seal::seal(&json, passphrase))
+        .map_err(|e| SniperError::Input(format!("could not write {}: {e}", path.display())))?;
+    Ok(count)
+}
+
+/// Restores the cookies saved in `path` into `browser`, checking they are for `portal`, and
+/// returns when they were saved
+pub async fn load(
+    browser: &Browser,
+    portal: &str,
+    path: &Path,
+    passphrase: &str,
+) -> Result<DateTime<Local>, SniperError> {
+    let sealed = std::fs::read(path)
+        .map_err(|e| SniperError::Input(format!("could not read {}: {e}", path.display())))?;
+    let json = seal::open(&sealed, passphrase).ok_or_else(|| {
+        SniperError::Input(format!(
+            "could not decrypt {}, the passphrase is wrong or it is not a session file",
+            path.display()
+        ))
+    })?;
+    let saved: SavedSession = serde_json::from_slice(&json)
+        .map_err(|e| SniperError::Input(format!("{} is damaged: {e}", path.display())))?;
+    if host(&saved.portal) != host(portal) {
+        Err(SniperError::Input(format!(
+            "{} was saved from {}, not {}",
+            path.display(),
+            host(&saved.portal),
+            host(portal)
+        )))?
+    }
+    browser.set_cookies(saved.cookies).await?;
+    Ok(saved.saved_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_the_host_from_a_url() {
+        assert_eq!(
+            host("https://saprod.emory.edu/psc/saprod/EMPLOYEE/SA/c/NUI_FRAMEWORK.PT_LANDINGPAGE.GBL"),
+            "saprod.emory.edu"
+        );
+        assert_eq!(host("http://127.0.0.1:8123/login"), "127.0.0.1");
+        assert_eq!(host("https://login.emory.edu?next=/"), "login.emory.edu");
+        assert_eq!(host("https://login.emory.edu#top"), "login.emory.edu");
+        assert_eq!(host("saprod.emory.edu/psc"), "saprod.emory.edu");
+    }
+
+    #[test]
+    fn keeps_cookies_for_the_login_hosts_and_duo() {
+        let hosts = ["saprod.emory.edu", "login.emory.edu"];
+        assert!(belongs("saprod.emory.edu", &hosts));
+        assert!(belongs("login.emory.edu", &hosts));
+        // sent to both, so part of the login
+        assert!(belongs(".emory.edu", &hosts));
+        assert!(belongs("api-1a2b3c.duosecurity.com", &hosts));
+        assert!(belongs(".duosecurity.com", &hosts));
+
+        // same parent domain, but never visited while logging in
+        assert!(!belongs("canvas.emory.edu", &hosts));
+        assert!(!belongs("mail.emory.edu", &hosts));
+        assert!(!belongs("notduosecurity.com", &hosts));
+        assert!(!belongs("duosecurity.com.evil.test", &hosts));
+        assert!(!belongs("tracker.test", &hosts));
+    }
+
+    #[test]
+    fn keeps_multi_label_suffixes_apart() {
+        let hosts = ["portal.ox.ac.uk", "sso.ox.ac.uk"];
+        assert!(belongs("portal.ox.ac.uk", &hosts));
+        assert!(belongs(".ox.ac.uk", &hosts));
+        assert!(!belongs("cam.ac.uk", &hosts));
+        assert!(!belongs("www.cam.ac.uk", &hosts));
+        assert!(!belongs("evilportal.ox.ac.uk", &hosts));
+    }
+}
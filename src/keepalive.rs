@@ -0,0 +1,44 @@
+use async_std::task::sleep;
+use chromiumoxide::Page;
+use chrono::{DateTime, Local, TimeDelta};
+use indicatif::ProgressBar;
+use std::time::Duration;
+
+use crate::clock::QUIET_PERIOD_SECS;
+use crate::error::SniperError;
+use crate::flow::Sniper;
+
+impl Sniper<'_> {
+    /// Touches the portal from a second tab every `keepalive` seconds so it does not log out
+    /// while waiting, logging back in when it already has. Stops with the quiet period before
+    /// `until` so nothing competes with the registration reload.
+    pub async fn keep_alive(&self, until: DateTime<Local>, pb: &ProgressBar) {
+        let Some(interval) = self.keepalive else {
+            return;
+        };
+        let stop = until - TimeDelta::seconds(QUIET_PERIOD_SECS);
+        let mut tab: Option<Page> = None;
+        while Local::now() + TimeDelta::seconds(interval as i64) < stop {
+            sleep(Duration::from_secs(interval)).await;
+            if let Err(e) = self.touch(&mut tab).await {
+                pb.println(format!("Keepalive failed: {e}"));
+            }
+        }
+        if let Some(tab) = tab {
+            let _ = tab.close().await;
+        }
+    }
+
+    /// Reloads the portal in the keepalive tab, opening it the first time
+    async fn touch(&self, tab: &mut Option<Page>) -> Result<(), SniperError> {
+        let page = match tab {
+            Some(page) => page,
+            None => {
+                let page = self.browser.new_page("about:blank").await?;
+                page.enable_stealth_mode().await?;
+                tab.insert(page)
+            }
+        };
+        self.resume_session(page).await
+    }
+}
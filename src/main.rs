@@ -1,43 +1,189 @@
-use async_std::task::sleep;
-use chromiumoxide::error::CdpError;
 use chromiumoxide::page::ScreenshotParams;
-use chromiumoxide::{Browser, BrowserConfig, Element, Page};
-use chrono::{Local, Timelike};
+use chromiumoxide::{Browser, BrowserConfig};
+use chrono::Local;
+use chrono_tz::Tz;
 use clap::Parser;
-use core::fmt;
-use elements::{EmoryPageElements, ToTable};
+use elements::{Course, EmoryPageElements, ToTable};
 use futures::StreamExt;
 use indicatif::{ProgressBar, ProgressStyle};
-use inquire::{MultiSelect, Password, PasswordDisplayMode, Select, Text};
+use inquire::validator::Validation;
+use inquire::{Confirm, MultiSelect, Select, Text};
 use std::borrow::Cow;
+use std::process::ExitCode;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 mod args;
-use args::SniperArgs;
+use args::{Command, SniperArgs};
 
 mod ascii;
+
+mod block;
+use block::ResourceBlocker;
 mod elements;
 
+mod error;
+use error::SniperError;
+
+mod clock;
+use clock::ServerClock;
+
+mod commands;
+
+mod creds;
+use creds::PasswordSources;
+
+mod flow;
+use flow::{Sniper, Target};
+
+mod plan;
+
+mod practice;
+use plan::Plan;
+use practice::{PracticePortal, Scenario};
+
+mod jar;
+
+mod keepalive;
+
+mod schedule;
+use schedule::RegistrationTime;
+
+mod school;
+
+mod seal;
+
+mod session;
+use session::Session;
+
+mod transition;
+
+mod wait;
+
 const TIMEOUT: u64 = 20;
 
 #[async_std::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() -> ExitCode {
     // get args
     let cli_args = SniperArgs::parse();
 
-    println!("\n{}\n", ascii::BANNER);
-    println!("Welcome to course-sniper, the precision registration tool.");
+    match snipe(cli_args).await {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::from(SniperError::exit_code_of(e.as_ref()))
+        }
+    }
+}
 
-    let pb = get_progress_bar("Enabling browser...");
+async fn snipe(cli_args: SniperArgs) -> Result<(), Box<dyn std::error::Error>> {
+    // plan files bring their own school, selectors, and timeouts
+    let plan =
+        match cli_args.command.as_ref().and_then(Command::plan) {
+            Some(path) => Some(Plan::from_file(path).map_err(|e| {
+                SniperError::Input(format!("could not load {}: {e}", path.display()))
+            })?),
+            None => None,
+        };
+    let school = plan
+        .as_ref()
+        .and_then(|plan| plan.school)
+        .unwrap_or(cli_args.school);
+    let timeouts = plan
+        .as_ref()
+        .map(|plan| (plan.timeouts.page, plan.timeouts.duo))
+        .unwrap_or((TIMEOUT, TIMEOUT));
+    let timezone = plan
+        .as_ref()
+        .and_then(|plan| plan.timezone)
+        .or(cli_args.timezone)
+        .or(school.timezone());
 
-    // setup browser
-    let (mut browser, mut handler) = if cli_args.attach {
-        Browser::launch(BrowserConfig::builder().with_head().build()?).await?
+    // page elements
+    let peoplesoft = if cli_args.practice {
+        let scenario = match &cli_args.practice_scenario {
+            Some(path) => Scenario::from_file(path).map_err(|e| {
+                SniperError::Input(format!("could not load {}: {e}", path.display()))
+            })?,
+            None => Scenario::default(),
+        };
+        let (username, password) = (scenario.username.clone(), scenario.password.clone());
+        let portal = PracticePortal::start(scenario)?;
+        eprintln!(
+            "Practice portal running on port {}, log in as {username} / {password}.",
+            portal.port
+        );
+        portal.config()
     } else {
-        Browser::launch(BrowserConfig::builder().build()?).await?
+        match plan.as_ref().and_then(|plan| plan.peoplesoft.clone()) {
+            Some(config) => config,
+            None => cli_args.peoplesoft_config(school),
+        }
     };
+    if peoplesoft.host.is_empty() || peoplesoft.site.is_empty() || peoplesoft.node.is_empty() {
+        Err(SniperError::Input(
+            "a PeopleSoft host, site and node are needed for this school".to_string(),
+        ))?
+    }
+    let mut elements = peoplesoft.elements();
+    if let Some(path) = plan
+        .as_ref()
+        .and_then(|plan| plan.selectors.as_ref())
+        .or(cli_args.selectors.as_ref())
+    {
+        elements = EmoryPageElements::from_file(path, elements)
+            .map_err(|e| SniperError::Input(format!("could not load {}: {e}", path.display())))?;
+    }
+
+    let sources = PasswordSources {
+        stdin: cli_args.password_stdin,
+        vault: cli_args.vault.clone().unwrap_or_else(creds::default_vault),
+    };
+    match &cli_args.command {
+        Some(Command::DumpSelectors { format }) => {
+            println!("{}", elements.to_string_pretty(*format)?);
+            return Ok(());
+        }
+        Some(Command::Creds { command }) => {
+            commands::creds(command, &sources.vault, cli_args.password_stdin)?;
+            return Ok(());
+        }
+        _ => (),
+    }
+    // check scripted inputs before spending time on the browser
+    if matches!(cli_args.command, Some(Command::CheckSession))
+        && cli_args.profile.is_none()
+        && cli_args.session_file.is_none()
+    {
+        Err(SniperError::Input(
+            "check-session needs a saved session from --profile or --session-file".to_string(),
+        ))?
+    }
+    match &cli_args.command {
+        Some(command) => {
+            commands::check(command, plan.as_ref(), timezone, &sources, cli_args.attach)?
+        }
+        None => {
+            println!("\n{}\n", ascii::BANNER);
+            println!("Welcome to course-sniper, the precision registration tool.");
+        }
+    }
+
+    let pb = get_progress_bar("Enabling browser...");
+
+    // setup browser
+    let mut config = BrowserConfig::builder();
+    if cli_args.attach {
+        config = config.with_head();
+    }
+    if let Some(profile) = &cli_args.profile {
+        config = config.user_data_dir(profile);
+    }
+    let config = config
+        .build()
+        .map_err(|e| SniperError::Input(format!("could not set up the browser: {e}")))?;
+    let (mut browser, mut handler) = Browser::launch(config).await?;
 
     let running = Arc::new(AtomicBool::new(true));
     let running_clone = running.clone();
@@ -50,454 +196,201 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     });
 
-    browser.clear_cookies().await?;
+    // a saved profile keeps its cookies, that is the point of it
+    if cli_args.profile.is_none() {
+        browser.clear_cookies().await?;
+    }
     pb.finish_with_message("Browser enabled.");
 
-    // page elements
-    let elements = elements::EmoryPageElements::default();
+    // school portal
+    let portal = school.portal(elements);
+
+    // restore a saved session before the portal is first opened
+    let session_file = cli_args
+        .session_file
+        .as_ref()
+        .or(cli_args.command.as_ref().and_then(Command::session_file));
+    if let Some(path) = session_file {
+        let saved_at =
+            jar::load(&browser, portal.page_url(), path, &jar::passphrase(false)?).await?;
+        eprintln!(
+            "Restored the session saved at {}.",
+            saved_at.format("%Y-%m-%d %H:%M:%S")
+        );
+    }
 
-    let page = browser.new_page(elements.page_url).await?;
+    let page = browser.new_page(portal.page_url()).await?;
     page.enable_stealth_mode().await?;
 
-    match run(&page, elements).await {
-        Ok(_) => (),
-        Err(e) => {
-            if cli_args.debug {
-                page.save_screenshot(
-                    ScreenshotParams::builder().full_page(true).build(),
-                    format!(
-                        "debug-{}.png",
-                        Local::now().format("%H:%M:%S.%3f").to_string()
-                    ),
-                )
-                .await?;
-            }
-            Err(e)?
+    // estimate the portal's clock from every response it sends
+    let clock = Arc::new(ServerClock::default());
+    if !cli_args.local_clock {
+        clock
+            .listen(&page, clock::origin(portal.page_url()))
+            .await?;
+    }
+
+    // drop what the enroll phase does not need, anything the flow reads always loads
+    let blocker = (!cli_args.no_block).then(|| {
+        ResourceBlocker::new(
+            &cli_args.block,
+            &cli_args.block_url,
+            portal.essential_resources(),
+        )
+    });
+
+    let session = Session::default();
+    let sniper = Sniper {
+        browser: &browser,
+        page: &page,
+        school: portal.as_ref(),
+        timeout: timeouts.0,
+        mfa_timeout: timeouts.1,
+        snipers: cli_args.snipers,
+        stagger: cli_args.stagger,
+        clock: (!cli_args.local_clock).then_some(clock.as_ref()),
+        fast_enroll: cli_args.fast_enroll,
+        blocker: blocker.as_ref(),
+        keepalive: (!cli_args.no_keepalive).then_some(cli_args.keepalive_interval),
+        session: &session,
+        duo_retries: cli_args.duo_retries,
+        duo_passcode: cli_args.duo_passcode.as_deref(),
+        saved_session: cli_args.profile.is_some() || session_file.is_some(),
+        output: cli_args.output,
+        debug: cli_args.debug,
+    };
+    let result = match &cli_args.command {
+        Some(command) => {
+            commands::execute(&sniper, command, plan.as_ref(), timezone, &sources).await
         }
+        None => run(&sniper, timezone, &sources).await,
+    };
+    if result.is_err() && cli_args.debug {
+        page.save_screenshot(
+            ScreenshotParams::builder().full_page(true).build(),
+            format!("debug-{}.png", Local::now().format("%H:%M:%S.%3f")),
+        )
+        .await?;
     }
 
-    // cleanup
-    browser.close().await?;
-    browser.try_wait()?;
+    // cleanup, closing even after an error so a --profile is written out
+    let closed = browser.close().await;
+    let exited = browser.try_wait();
     running.store(false, Ordering::Relaxed);
     handle.await;
+    result?;
+    closed?;
+    exited?;
     Ok(())
 }
 
-async fn run(page: &Page, elements: EmoryPageElements) -> Result<(), Box<dyn std::error::Error>> {
+async fn run(
+    sniper: &Sniper<'_>,
+    timezone: Option<Tz>,
+    sources: &PasswordSources,
+) -> Result<(), Box<dyn std::error::Error>> {
     // login info
-    let user_name = Text::new("Username: ").prompt()?;
-    let user_pwd = Password::new("Password: ")
-        .with_display_mode(PasswordDisplayMode::Masked)
-        .without_confirmation()
-        .prompt()?;
-
-    let pb = get_progress_bar("Logging in with credentials...");
-
-    // login
-    page.wait_for_navigation()
-        .await?
-        .find_element(elements.username_input)
-        .await?
-        .click()
-        .await?
-        .type_str(user_name)
-        .await?;
-    page.find_element(elements.passwd_input)
-        .await?
-        .click()
-        .await?
-        .type_str(user_pwd)
-        .await?
-        .press_key("Enter")
+    let user_name = match std::env::var(creds::USER_ENV) {
+        Ok(user_name) => user_name,
+        Err(_) => Text::new("Username: ").prompt()?,
+    };
+    sniper
+        .login(&user_name, &sources.password(&user_name)?)
         .await?;
 
-    // authentication transition
-    match authentication_transition(&page, &elements, TIMEOUT).await {
-        Ok(status) => match status {
-            AuthTransition::AuthSuccess => pb.finish_with_message("Authenticated."),
-            AuthTransition::AuthFail => {
-                pb.finish_with_message("Invalid credentials.");
-                return Ok(());
-            }
-            AuthTransition::Duo => {
-                pb.finish_with_message("Duo authentication required.");
-                let pb = get_progress_bar("Waiting for Duo confirmation...");
-                match duo_transition(&page, &elements, TIMEOUT).await {
-                    Ok(status) => match status {
-                        DuoTransition::Trust => pb.finish_with_message("Authenticated."),
-                        DuoTransition::TimeOut => {
-                            pb.finish_with_message("Duo authentication timed out.");
-                            return Ok(());
-                        }
-                        DuoTransition::Cart => pb.finish_with_message("Authenticated."),
-                    },
-                    Err(e) => {
-                        pb.finish_with_message("Failed to find the correct elements or timed out.");
-                        Err(e)?
-                    }
-                }
-            }
-        },
-        Err(e) => {
-            pb.finish_with_message("Failed to find the correct elements or timed out.");
-            Err(e)?
-        }
-    }
-
     // pick a shopping cart
-    let pb = get_progress_bar("Looking for shopping cart...");
-    match cart_transition(&page, &elements, TIMEOUT).await {
-        Ok(status) => match status {
-            CartTransition::In => pb.finish_with_message("Entered shopping cart."),
-            CartTransition::Select => {
-                pb.finish_with_message("Shopping carts found.");
-                let carts = elements.get_shopping_carts(&page).await?;
-                let selected_cart = Select::new("Select a cart:", carts).prompt()?;
-                selected_cart.element.click().await?;
-            }
-        },
-        Err(e) => {
-            pb.finish_with_message("Failed to find the correct elements or timed out.");
-            Err(e)?
-        }
+    if let Some(carts) = sniper.find_carts().await? {
+        let selected_cart = Select::new("Select a cart:", carts).prompt()?;
+        sniper
+            .session
+            .remember_term(Some(selected_cart.text.trim()));
+        selected_cart.element.click().await?;
     }
 
     // get course info
-    let pb = get_progress_bar("Fetching courses in cart...");
-    wait_element_agressive_retry(&page, elements.course_row, TIMEOUT).await?;
-    let courses = elements.get_cart_courses(&page).await?;
-    pb.finish_with_message(format!("Found {} courses.", courses.len()));
+    let courses = sniper.get_courses().await?;
     println!("{}", courses.to_table());
 
     // pick courses
-    let selected_courses = MultiSelect::new("Select courses:", courses).prompt()?;
+    let selected_courses = MultiSelect::new("Select courses:", courses.clone()).prompt()?;
 
     // pick validate or enroll
     if Select::new("Select action:", vec!["Validate", "Enroll"]).prompt()? == "Enroll" {
-        //TODO improve registration time selection and implimentation
-        let registration_times: Vec<RegistrationTime> = (1..=12)
-            .flat_map(|hour| {
-                (0..60).flat_map(move |minute| {
-                    [true, false]
-                        .iter()
-                        .map(move |&am| RegistrationTime(hour, minute, am))
+        let registration_time = loop {
+            let at = Text::new("Registration time:")
+                .with_help_message(&format!(
+                    "e.g. 2027-01-10 08:00:00.250 in {}, or +5m from now",
+                    timezone
+                        .map(|timezone| timezone.to_string())
+                        .unwrap_or("local time".to_string())
+                ))
+                .with_validator(|input: &str| {
+                    Ok(match input.parse::<RegistrationTime>() {
+                        Ok(_) => Validation::Valid,
+                        Err(e) => Validation::Invalid(e.into()),
+                    })
                 })
-            })
-            .collect();
-        let registration_time =
-            Select::new("Select registration time:", registration_times).prompt()?;
-        let pb = get_progress_bar(format!(
-            "Waiting for registration time: {registration_time}..."
-        ));
-        let registration_hour = if registration_time.2 {
-            registration_time.0
-        } else {
-            registration_time.0 + 12
-        };
-        loop {
-            let now = Local::now();
-            // if registration break
-            if now.hour() == registration_hour && now.minute() == registration_time.1 {
-                break;
-            } else if now.hour() == registration_hour
-                && now.minute() == registration_time.1 - 1
-                && now.second() >= 50
-            {
-                // if 10 seconds off stop sleeping
-                continue;
-            } else {
-                // if far away sleep
-                sleep(Duration::from_secs(4)).await;
-            }
-        }
-        pb.finish_with_message(format!(
-            "Reloaded for registration at {}.",
-            Local::now().format("%H:%M:%S.%3f")
-        ));
-
-        page.reload().await?.wait_for_navigation().await?;
-
-        println!(
-            "Page finished loading at {}",
-            Local::now().format("%H:%M:%S.%3f")
-        );
-        let pb = get_progress_bar("Selecting courses...");
-        for (index, checkbox) in wait_elements_agressive_retry(&page, elements.checkboxes, TIMEOUT)
-            .await?
-            .into_iter()
-            .enumerate()
-        {
-            if selected_courses
-                .iter()
-                .any(|course| course.checkbox_index == index as u8)
-            {
-                checkbox.click().await?;
+                .prompt()?
+                .parse::<RegistrationTime>()?;
+            match schedule::registration_time(Some(at), timezone) {
+                Ok(registration_time) => break registration_time,
+                Err(e) => println!("{e}"),
             }
-        }
-        pb.finish_with_message("Courses selected.");
-
-        // enroll
-        wait_element_agressive_retry(&page, elements.enroll_button, TIMEOUT)
-            .await?
-            .click()
-            .await?;
-
-        println!(
-            "Enroll clicked at {}",
-            Local::now().format("%H:%M:%S.%3f").to_string()
-        );
-
-        // confirm
-        wait_element_agressive_retry(&page, &elements.enroll_confirm_button, TIMEOUT)
-            .await?
-            .click()
+        };
+        let targets = pick_fallbacks(&courses, selected_courses)?;
+        let wanted = targets.len();
+        let registration_results = sniper
+            .enroll_with_fallbacks(targets, registration_time)
             .await?;
-
-        println!(
-            "Confirm clicked at {}",
-            Local::now().format("%H:%M:%S.%3f").to_string()
-        );
-
-        // results
-        let pb = get_progress_bar("Waiting for enrollment results...");
-        wait_element_agressive_retry(&page, elements.results_rows, TIMEOUT).await?;
-        let registration_results = elements.get_registration_results(&page).await?;
-        pb.finish_with_message(format!(
-            "Found {} enrollment results.",
-            registration_results.len()
-        ));
         println!("{}", registration_results.to_table());
+        SniperError::check_enrollment(&registration_results, wanted)?;
     } else {
-        let pb = get_progress_bar("Selecting courses...");
-        for (index, checkbox) in wait_elements_agressive_retry(&page, elements.checkboxes, TIMEOUT)
-            .await?
-            .into_iter()
-            .enumerate()
-        {
-            if selected_courses
-                .iter()
-                .any(|course| course.checkbox_index == index as u8)
-            {
-                checkbox.click().await?;
-            }
-        }
-        pb.finish_with_message("Courses selected.");
-
-        // validate
-        wait_element_agressive_retry(&page, elements.validate_button, TIMEOUT)
-            .await?
-            .click()
-            .await?;
-
-        println!(
-            "Validation clicked at {}",
-            Local::now().format("%H:%M:%S.%3f").to_string()
-        );
-        // results
-        let pb = get_progress_bar("Waiting for validation results...");
-        wait_element_agressive_retry(&page, elements.results_rows, TIMEOUT).await?;
-        let registration_results = elements.get_registration_results(&page).await?;
-        pb.finish_with_message(format!(
-            "Found {} validation results.",
-            registration_results.len()
-        ));
+        let registration_results = sniper.validate(&selected_courses).await?;
         println!("{}", registration_results.to_table());
     }
 
     Ok(())
 }
 
-enum CartTransition {
-    In,
-    Select,
-}
-
-enum AuthTransition {
-    AuthSuccess,
-    Duo,
-    AuthFail,
-}
-
-enum DuoTransition {
-    TimeOut,
-    Trust,
-    Cart,
-}
-
-async fn authentication_transition(
-    page: &Page,
-    elements: &EmoryPageElements,
-    wait_time: u64,
-) -> Result<AuthTransition, CdpError> {
-    let start = Instant::now();
-    let wait_time = Duration::new(wait_time, 0);
-    loop {
-        match page.find_element(elements.login_error).await {
-            Ok(_) => return Ok(AuthTransition::AuthFail),
-            Err(e) => {
-                if start.elapsed() >= wait_time {
-                    return Err(e);
-                }
-            }
-        }
-        match page.find_element(elements.duo_waiting).await {
-            Ok(_) => return Ok(AuthTransition::Duo),
-            Err(e) => {
-                if start.elapsed() >= wait_time {
-                    return Err(e);
-                }
-            }
-        }
-        match page.find_element(elements.semester_cart).await {
-            Ok(_) => return Ok(AuthTransition::AuthSuccess),
-            Err(e) => {
-                if start.elapsed() >= wait_time {
-                    return Err(e);
-                }
-            }
-        }
-        match page.find_element(elements.course_row).await {
-            Ok(_) => return Ok(AuthTransition::AuthSuccess),
-            Err(e) => {
-                if start.elapsed() >= wait_time {
-                    return Err(e);
-                }
-            }
-        }
-        sleep(Duration::from_millis(100)).await;
-    }
-}
-
-async fn duo_transition(
-    page: &Page,
-    elements: &EmoryPageElements,
-    wait_time: u64,
-) -> Result<DuoTransition, CdpError> {
-    let start = Instant::now();
-    let wait_time = Duration::new(wait_time, 0);
-    loop {
-        match page.find_element(elements.duo_trust_browser).await {
-            Ok(element) => {
-                element.click().await?;
-                return Ok(DuoTransition::Trust);
-            }
-            Err(e) => {
-                if start.elapsed() >= wait_time {
-                    return Err(e);
-                }
-            }
-        }
-        match page.find_element(elements.duo_time_out_try_again).await {
-            Ok(_) => return Ok(DuoTransition::TimeOut),
-            Err(e) => {
-                if start.elapsed() >= wait_time {
-                    return Err(e);
-                }
-            }
-        }
-        match page.find_element(elements.semester_cart).await {
-            Ok(_) => return Ok(DuoTransition::Cart),
-            Err(e) => {
-                if start.elapsed() >= wait_time {
-                    return Err(e);
-                }
-            }
-        }
-        match page.find_element(elements.course_row).await {
-            Ok(_) => return Ok(DuoTransition::Cart),
-            Err(e) => {
-                if start.elapsed() >= wait_time {
-                    return Err(e);
-                }
-            }
-        }
-        sleep(Duration::from_millis(100)).await;
-    }
-}
-
-async fn cart_transition(
-    page: &Page,
-    elements: &EmoryPageElements,
-    wait_time: u64,
-) -> Result<CartTransition, CdpError> {
-    let start = Instant::now();
-    let wait_time = Duration::new(wait_time, 0);
-    loop {
-        match page.find_element(elements.semester_cart).await {
-            Ok(_) => return Ok(CartTransition::Select),
-            Err(e) => {
-                if start.elapsed() >= wait_time {
-                    return Err(e);
-                }
-            }
-        }
-        match page.find_element(elements.course_row).await {
-            Ok(_) => return Ok(CartTransition::In),
-            Err(e) => {
-                if start.elapsed() >= wait_time {
-                    return Err(e);
-                }
-            }
-        }
-        sleep(Duration::from_millis(100)).await;
-    }
-}
-
-async fn wait_element_agressive_retry(
-    page: &Page,
-    selector: &str,
-    wait_time: u64,
-) -> Result<Element, CdpError> {
-    let start = Instant::now();
-    let wait_time = Duration::new(wait_time, 0);
-    loop {
-        match page.find_element(selector).await {
-            Ok(element) => return Ok(element),
-            Err(e) => {
-                if start.elapsed() < wait_time {
-                    continue;
-                } else {
-                    return Err(e);
+/// Optionally lines up, in order, the alternates to try for each selected course
+fn pick_fallbacks(
+    courses: &[Course],
+    selected_courses: Vec<Course>,
+) -> Result<Vec<Target>, Box<dyn std::error::Error>> {
+    let with_fallbacks = Confirm::new("Add fallback courses?")
+        .with_default(false)
+        .prompt()?;
+    let mut targets = Vec::new();
+    for course in selected_courses {
+        let mut fallbacks: Vec<Course> = Vec::new();
+        if with_fallbacks {
+            loop {
+                let options: Vec<Course> = courses
+                    .iter()
+                    .filter(|option| {
+                        !option.same_class(&course)
+                            && !fallbacks.iter().any(|fallback| fallback.same_class(option))
+                    })
+                    .cloned()
+                    .collect();
+                if options.is_empty() {
+                    break;
                 }
-            }
-        }
-    }
-}
-
-async fn wait_elements_agressive_retry(
-    page: &Page,
-    selector: &str,
-    wait_time: u64,
-) -> Result<Vec<Element>, CdpError> {
-    let start = Instant::now();
-    let wait_time = Duration::new(wait_time, 0);
-    loop {
-        match page.find_elements(selector).await {
-            Ok(element) => return Ok(element),
-            Err(e) => {
-                if start.elapsed() < wait_time {
-                    continue;
-                } else {
-                    return Err(e);
+                match Select::new(
+                    &format!("Fallback #{} for {course}:", fallbacks.len() + 1),
+                    options,
+                )
+                .with_help_message("esc when done")
+                .prompt_skippable()?
+                {
+                    Some(fallback) => fallbacks.push(fallback),
+                    None => break,
                 }
             }
         }
+        targets.push(Target { course, fallbacks });
     }
-}
-
-struct RegistrationTime(u32, u32, bool);
-
-impl fmt::Display for RegistrationTime {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "{:02}:{:02} {}",
-            self.0,
-            self.1,
-            if self.2 { "AM" } else { "PM" }
-        )
-    }
+    Ok(targets)
 }
 
 fn get_progress_bar(msg: impl Into<Cow<'static, str>>) -> ProgressBar {
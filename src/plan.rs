@@ -0,0 +1,230 @@
+use chrono_tz::Tz;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use crate::elements::Course;
+use crate::error::SniperError;
+use crate::flow::{self, Target};
+use crate::schedule::RegistrationTime;
+use crate::school::{PeopleSoftConfig, School};
+use crate::TIMEOUT;
+
+/// Every choice `run` would prompt for, written down ahead of the registration window
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Plan {
+    /// Falls back to --school when left out
+    pub school: Option<School>,
+    /// Replaces the school's PeopleSoft preset
+    pub peoplesoft: Option<PeopleSoftConfig>,
+    /// Selector file, relative paths are resolved from the plan's directory
+    pub selectors: Option<PathBuf>,
+    /// Falls back to COURSE_SNIPER_USER and then a prompt when left out
+    pub username: Option<String>,
+    /// Shopping cart to open, matched against the cart name
+    pub term: Option<String>,
+    pub action: PlanAction,
+    /// Registration time in `timezone`, or an offset like "+5m", enrolls immediately when left out
+    #[serde(default, deserialize_with = "deserialize_at")]
+    pub at: Option<RegistrationTime>,
+    /// Timezone `at` is given in, falls back to --timezone and then the school's timezone
+    pub timezone: Option<Tz>,
+    #[serde(default)]
+    pub timeouts: Timeouts,
+    pub courses: Vec<PlanCourse>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PlanAction {
+    Validate,
+    Enroll,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Timeouts {
+    /// Seconds to wait on each page transition
+    #[serde(default = "default_timeout")]
+    pub page: u64,
+    /// Seconds to wait for the Duo push to be approved
+    #[serde(default = "default_timeout")]
+    pub duo: u64,
+}
+
+/// Accepts a quoted datetime or a bare TOML local datetime like `at = 2027-01-10T08:00:00`
+fn deserialize_at<'de, D>(deserializer: D) -> Result<Option<RegistrationTime>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum At {
+        Text(String),
+        Toml(toml::value::Datetime),
+    }
+
+    let text = match At::deserialize(deserializer)? {
+        At::Text(text) => text,
+        At::Toml(datetime) => datetime.to_string(),
+    };
+    text.parse().map(Some).map_err(D::Error::custom)
+}
+
+fn default_timeout() -> u64 {
+    TIMEOUT
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            page: TIMEOUT,
+            duo: TIMEOUT,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PlanCourse {
+    /// Class number or part of the course description
+    pub class: String,
+    /// Tried in order when enrolling in `class` fails
+    #[serde(default)]
+    pub fallbacks: Vec<String>,
+}
+
+impl Plan {
+    /// Reads a plan, `.yaml` and `.yml` files are read as YAML and anything else as TOML
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let contents = std::fs::read_to_string(path)?;
+        let mut plan: Plan = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml" | "yml") => serde_yaml::from_str(&contents)?,
+            _ => toml::from_str(&contents)?,
+        };
+        if plan.courses.is_empty() {
+            Err(format!("{} does not list any courses", path.display()))?
+        }
+        if let (Some(selectors), Some(dir)) = (&plan.selectors, path.parent()) {
+            plan.selectors = Some(dir.join(selectors));
+        }
+        Ok(plan)
+    }
+
+    /// Matches every planned course and its fallbacks against the cart
+    pub fn targets(&self, courses: &[Course]) -> Result<Vec<Target>, SniperError> {
+        self.courses
+            .iter()
+            .map(|planned| {
+                Ok(Target {
+                    course: flow::match_course(courses, &planned.class)?,
+                    fallbacks: flow::match_courses(courses, &planned.fallbacks)?,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `contents` to a plan file named `name` and reads it back
+    fn plan(name: &str, contents: &str) -> Result<Plan, Box<dyn Error>> {
+        let path = std::env::temp_dir().join(format!("course-sniper-test-{name}"));
+        std::fs::write(&path, contents).unwrap();
+        let plan = Plan::from_file(&path);
+        let _ = std::fs::remove_file(&path);
+        plan
+    }
+
+    fn at(plan: &Plan) -> String {
+        match plan.at {
+            Some(RegistrationTime::At(at)) => at.to_string(),
+            other => panic!("expected a wall clock time, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn reads_bare_toml_datetimes() {
+        let plan = plan(
+            "bare.toml",
+            r#"
+action = "enroll"
+at = 2027-01-10T08:00:00.250
+timezone = "America/New_York"
+selectors = "selectors.toml"
+
+[[courses]]
+class = "4321"
+fallbacks = ["4322"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(plan.action, PlanAction::Enroll);
+        assert_eq!(at(&plan), "2027-01-10 08:00:00.250");
+        assert_eq!(plan.timezone, Some(Tz::America__New_York));
+        assert_eq!(plan.courses[0].fallbacks, vec!["4322".to_string()]);
+        assert_eq!(
+            plan.selectors,
+            Some(std::env::temp_dir().join("selectors.toml"))
+        );
+        assert_eq!(plan.timeouts.page, TIMEOUT);
+    }
+
+    #[test]
+    fn reads_quoted_toml_datetimes() {
+        let plan = plan(
+            "quoted.toml",
+            "action = \"validate\"\nat = \"2027-01-10 08:00\"\n[[courses]]\nclass = \"CS 170\"\n",
+        )
+        .unwrap();
+        assert_eq!(at(&plan), "2027-01-10 08:00:00");
+    }
+
+    #[test]
+    fn reads_yaml() {
+        let plan = plan(
+            "plan.yaml",
+            r#"
+username: jdoe
+term: Spring 2027
+action: enroll
+at: "2027-01-10T08:00:00"
+timeouts:
+  duo: 60
+courses:
+  - class: "4321"
+    fallbacks: ["4322", "4323"]
+  - class: MATH 221
+"#,
+        )
+        .unwrap();
+        assert_eq!(plan.username.as_deref(), Some("jdoe"));
+        assert_eq!(plan.term.as_deref(), Some("Spring 2027"));
+        assert_eq!(at(&plan), "2027-01-10 08:00:00");
+        assert_eq!((plan.timeouts.page, plan.timeouts.duo), (TIMEOUT, 60));
+        assert_eq!(plan.courses.len(), 2);
+    }
+
+    #[test]
+    fn rejects_bad_plans() {
+        let empty = plan("empty.toml", "action = \"validate\"\ncourses = []\n").unwrap_err();
+        assert!(
+            empty.to_string().contains("does not list any courses"),
+            "{empty}"
+        );
+        assert!(plan(
+            "unknown.toml",
+            "action = \"validate\"\nsnipe = true\n[[courses]]\nclass = \"4321\"\n"
+        )
+        .is_err());
+        assert!(plan(
+            "bad-at.toml",
+            "action = \"enroll\"\nat = \"soon\"\n[[courses]]\nclass = \"4321\"\n"
+        )
+        .is_err());
+    }
+}
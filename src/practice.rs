@@ -0,0 +1,825 @@
+use serde::Deserialize;
+use std::error::Error;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::school::PeopleSoftConfig;
+
+/// Site the practice portal pretends to be, `practice_1` puts results in `win1div`
+const SITE: &str = "practice_1";
+const CACHE: &str = "/cs/practice/cache";
+const SUCCESS_ICON: &str = "PS_CS_STATUS_SUCCESS_ICN_1.gif";
+const ERROR_ICON: &str = "PS_CS_STATUS_ERROR_ICN_1.gif";
+/// Cookie that carries a login over to later runs on the same browser profile
+const SESSION_COOKIE: &str = "PS_TOKEN";
+/// 1x1 transparent gif served for the status icons
+const GIF: &[u8] = &[
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x02, 0x44, 0x01, 0x00, 0x3b,
+];
+
+/// What the practice portal serves, read from a TOML scenario file
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct Scenario {
+    pub username: String,
+    pub password: String,
+    pub duo: DuoScenario,
+    /// Milliseconds before the Duo prompt is approved or times out
+    pub duo_delay: u64,
+    /// Pushes that time out before one is approved, with `duo = "approve"`
+    pub duo_timeouts: u32,
+    /// Passcode the Duo prompt accepts through other options
+    pub duo_passcode: String,
+    /// Milliseconds added before every response
+    pub latency: u64,
+    /// Seconds without a shopping cart request before the session expires, never when left out
+    pub idle_timeout: Option<u64>,
+    pub carts: Vec<PracticeCart>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DuoScenario {
+    /// Goes straight to the shopping cart after logging in
+    None,
+    /// Shows the trust browser prompt once `duo_delay` has passed
+    Approve,
+    /// Shows the timed out prompt once `duo_delay` has passed
+    Timeout,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PracticeCart {
+    pub term: String,
+    /// Seconds after the portal starts that enrollment opens
+    #[serde(default)]
+    pub opens_in: u64,
+    pub courses: Vec<PracticeCourse>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PracticeCourse {
+    pub class_number: String,
+    pub description: String,
+    pub seats: u32,
+    pub capacity: u32,
+    /// Full courses show a wait list instead of being closed
+    #[serde(default)]
+    pub waitlist: bool,
+    #[serde(default = "default_schedule")]
+    pub schedule: String,
+    #[serde(default = "default_room")]
+    pub room: String,
+    #[serde(default = "default_instructor")]
+    pub instructor: String,
+    #[serde(default = "default_credits")]
+    pub credits: String,
+}
+
+fn default_schedule() -> String {
+    "MoWe 10:00AM - 11:15AM".to_string()
+}
+
+fn default_room() -> String {
+    "Practice Hall 101".to_string()
+}
+
+fn default_instructor() -> String {
+    "Staff".to_string()
+}
+
+fn default_credits() -> String {
+    "3.00".to_string()
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        let course = |class_number: &str, description: &str, seats, capacity| PracticeCourse {
+            class_number: class_number.to_string(),
+            description: description.to_string(),
+            seats,
+            capacity,
+            waitlist: false,
+            schedule: default_schedule(),
+            room: default_room(),
+            instructor: default_instructor(),
+            credits: default_credits(),
+        };
+        Self {
+            username: "student".to_string(),
+            password: "practice".to_string(),
+            duo: DuoScenario::Approve,
+            duo_delay: 2000,
+            duo_timeouts: 0,
+            duo_passcode: "123456".to_string(),
+            latency: 50,
+            idle_timeout: None,
+            carts: vec![
+                PracticeCart {
+                    term: "Spring 2027".to_string(),
+                    opens_in: 60,
+                    courses: vec![
+                        course(
+                            "4321",
+                            "CS 170-1 Introduction to Computer Science I",
+                            5,
+                            120,
+                        ),
+                        course(
+                            "4322",
+                            "CS 170-2 Introduction to Computer Science I",
+                            1,
+                            120,
+                        ),
+                        course("5510", "MATH 221-3 Linear Algebra", 0, 35),
+                        course("6120", "PHYS 141-1 Mechanics", 12, 80),
+                    ],
+                },
+                PracticeCart {
+                    term: "Summer 2027".to_string(),
+                    opens_in: 0,
+                    courses: vec![course("7001", "ENG 101-1 Expository Writing", 3, 20)],
+                },
+            ],
+        }
+    }
+}
+
+impl Scenario {
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let scenario: Scenario = toml::from_str(&std::fs::read_to_string(path)?)?;
+        if scenario.carts.is_empty() {
+            Err(format!("{} does not have any carts", path.display()))?
+        }
+        Ok(scenario)
+    }
+}
+
+#[derive(PartialEq, Eq)]
+enum Session {
+    LoggedOut,
+    Duo,
+    LoggedIn,
+}
+
+struct State {
+    scenario: Scenario,
+    started: Instant,
+    session: Session,
+    /// Last shopping cart request, for `idle_timeout`
+    last_seen: Instant,
+    /// Duo pushes sent since the last login, for `duo_timeouts`
+    pushes: u32,
+    /// Bumped whenever the session expires so older session cookies stop working
+    generation: u32,
+    /// Results of the last enroll or validate, shown once on the next cart page
+    results: Vec<(String, bool)>,
+}
+
+impl State {
+    /// Session cookie value, the same for every run of a scenario until a session expires, so
+    /// a browser profile stays logged in across runs without the portal keeping anything
+    fn token(&self) -> String {
+        format!("practice-{}-{}", self.scenario.username, self.generation)
+    }
+
+    /// Marks the session logged in and returns the cookie that keeps it that way
+    fn log_in(&mut self) -> Header {
+        self.session = Session::LoggedIn;
+        self.last_seen = Instant::now();
+        header(
+            "Set-Cookie",
+            &format!(
+                "{SESSION_COOKIE}={}; Path=/; Max-Age=86400; HttpOnly",
+                self.token()
+            ),
+        )
+    }
+}
+
+/// Local stand in for a PeopleSoft shopping cart, with the same markup the selectors expect
+pub struct PracticePortal {
+    pub port: u16,
+}
+
+impl PracticePortal {
+    /// Starts serving `scenario` on a free local port in the background
+    pub fn start(scenario: Scenario) -> Result<Self, Box<dyn Error>> {
+        let server = Server::http("127.0.0.1:0").map_err(|e| e.to_string())?;
+        let port = server
+            .server_addr()
+            .to_ip()
+            .ok_or("practice portal is not listening on a port")?
+            .port();
+        let state = Arc::new(Mutex::new(State {
+            scenario,
+            started: Instant::now(),
+            session: Session::LoggedOut,
+            last_seen: Instant::now(),
+            pushes: 0,
+            generation: 0,
+            results: Vec::new(),
+        }));
+        let config = PracticePortal { port }.config();
+        std::thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let state = state.clone();
+                let config = config.clone();
+                std::thread::spawn(move || handle(request, &state, &config));
+            }
+        });
+        Ok(PracticePortal { port })
+    }
+
+    /// PeopleSoft settings pointing at this portal
+    pub fn config(&self) -> PeopleSoftConfig {
+        PeopleSoftConfig {
+            host: format!("http://127.0.0.1:{}", self.port),
+            site: SITE.to_string(),
+            node: "SA".to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+fn handle(mut request: Request, state: &Mutex<State>, config: &PeopleSoftConfig) {
+    let latency = state.lock().unwrap().scenario.latency;
+    std::thread::sleep(Duration::from_millis(latency));
+
+    let cart_path = format!(
+        "/psc/{}/{}/{}/c/SSR_STUDENT_FL.SSR_SHOP_CART_FL.GBL",
+        config.site, config.portal, config.node
+    );
+    let (path, query) = request
+        .url()
+        .split_once('?')
+        .map(|(path, query)| (path.to_string(), query.to_string()))
+        .unwrap_or((request.url().to_string(), String::new()));
+    let mut body = String::new();
+    if *request.method() == Method::Post {
+        let _ = request.as_reader().read_to_string(&mut body);
+    }
+    let form = parse_form(&body);
+    let query = parse_form(&query);
+    let term = query
+        .iter()
+        .find(|(name, _)| name == "term")
+        .and_then(|(_, term)| term.parse::<usize>().ok());
+
+    let cookie = request
+        .headers()
+        .iter()
+        .filter(|header| header.field.equiv("Cookie"))
+        .flat_map(|header| header.value.as_str().split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, value)| value.to_string());
+
+    let mut guard = state.lock().unwrap();
+    let state = &mut *guard;
+    // a login from an earlier run on the same profile
+    if state.session == Session::LoggedOut && cookie.as_deref() == Some(state.token().as_str()) {
+        state.session = Session::LoggedIn;
+        state.last_seen = Instant::now();
+    }
+    let mut expired = false;
+    if path == cart_path {
+        let idle = state
+            .scenario
+            .idle_timeout
+            .is_some_and(|timeout| state.last_seen.elapsed() >= Duration::from_secs(timeout));
+        if idle && state.session == Session::LoggedIn {
+            state.session = Session::LoggedOut;
+            state.generation += 1;
+            expired = true;
+        }
+        state.last_seen = Instant::now();
+    }
+    let response = match (request.method(), path.as_str()) {
+        (Method::Get, "/login") => html(login_page(false)),
+        (Method::Get, "/expire") => html(expire_page()),
+        (Method::Post, "/login") => {
+            let field = |name: &str| {
+                form.iter()
+                    .find(|(field, _)| field == name)
+                    .map(|(_, value)| value.as_str())
+            };
+            if field("userid") == Some(state.scenario.username.as_str())
+                && field("pwd") == Some(state.scenario.password.as_str())
+            {
+                if state.scenario.duo == DuoScenario::None {
+                    let cookie = state.log_in();
+                    redirect(&cart_path).with_header(cookie)
+                } else {
+                    state.session = Session::Duo;
+                    state.pushes = 0;
+                    redirect("/duo")
+                }
+            } else {
+                html(login_page(true))
+            }
+        }
+        (Method::Get, "/duo") => match state.session {
+            Session::Duo => {
+                // every load of the prompt sends a new push
+                state.pushes += 1;
+                let times_out = state.scenario.duo == DuoScenario::Timeout
+                    || state.pushes <= state.scenario.duo_timeouts;
+                html(duo_page(&state.scenario, times_out, &cart_path))
+            }
+            _ => redirect("/login"),
+        },
+        (Method::Get, "/duo/options") => match state.session {
+            Session::Duo => html(duo_options_page()),
+            _ => redirect("/login"),
+        },
+        (Method::Get, "/duo/passcode") => match state.session {
+            Session::Duo => html(passcode_page(false)),
+            _ => redirect("/login"),
+        },
+        (Method::Post, "/duo/passcode") => match state.session {
+            Session::Duo => {
+                let passcode = form
+                    .iter()
+                    .find(|(field, _)| field == "passcode-input")
+                    .map(|(_, value)| value.as_str());
+                if passcode == Some(state.scenario.duo_passcode.as_str()) {
+                    let cookie = state.log_in();
+                    html(page("Duo", TRUST_PROMPT)).with_header(cookie)
+                } else {
+                    html(passcode_page(true))
+                }
+            }
+            _ => redirect("/login"),
+        },
+        (Method::Get, "/duo/trust") => match state.session {
+            Session::Duo if state.scenario.duo == DuoScenario::Approve => {
+                let cookie = state.log_in();
+                redirect(&cart_path).with_header(cookie)
+            }
+            // already approved with a passcode
+            Session::LoggedIn => redirect(&cart_path),
+            _ => redirect("/login"),
+        },
+        (_, path) if path == cart_path => match state.session {
+            Session::LoggedIn => {
+                let term = match term {
+                    Some(term) if term < state.scenario.carts.len() => Some(term),
+                    _ if state.scenario.carts.len() == 1 => Some(0),
+                    _ => None,
+                };
+                match (request.method(), term) {
+                    (_, None) => html(cart_select_page(&state.scenario, &cart_path)),
+                    (Method::Post, Some(term)) => {
+                        let enroll = query
+                            .iter()
+                            .any(|(name, action)| name == "action" && action == "enroll");
+                        let selected: Vec<&str> = form
+                            .iter()
+                            .filter(|(name, _)| name == "select")
+                            .map(|(_, class_number)| class_number.as_str())
+                            .collect();
+                        state.results = submit(
+                            &mut state.scenario.carts[term],
+                            &selected,
+                            enroll,
+                            state.started,
+                        );
+                        redirect(&format!("{cart_path}?term={term}"))
+                    }
+                    (_, Some(term)) => {
+                        let results = std::mem::take(&mut state.results);
+                        html(cart_page(
+                            &state.scenario.carts[term],
+                            term,
+                            &results,
+                            &cart_path,
+                        ))
+                    }
+                }
+            }
+            // like PeopleSoft, an idle session gets the timeout page rather than the login form
+            _ if expired => redirect("/expire").with_header(header(
+                "Set-Cookie",
+                &format!("{SESSION_COOKIE}=; Path=/; Max-Age=0"),
+            )),
+            _ => redirect("/login"),
+        },
+        (Method::Get, path) if path.starts_with(CACHE) && path.ends_with(".gif") => {
+            Response::from_data(GIF).with_header(header("Content-Type", "image/gif"))
+        }
+        _ => Response::from_data("Not Found").with_status_code(404),
+    };
+    drop(guard);
+    let _ = request.respond(response);
+}
+
+/// Checks every selected course, and for an enroll takes a seat in the ones that succeed
+fn submit(
+    cart: &mut PracticeCart,
+    selected: &[&str],
+    enroll: bool,
+    started: Instant,
+) -> Vec<(String, bool)> {
+    let open = started.elapsed() >= Duration::from_secs(cart.opens_in);
+    let mut results = Vec::new();
+    for class_number in selected {
+        let Some(index) = cart
+            .courses
+            .iter()
+            .position(|course| course.class_number == *class_number)
+        else {
+            continue;
+        };
+        let course = &mut cart.courses[index];
+        let (message, success) = if !open {
+            ("Enrollment for this term is not open yet.", false)
+        } else if course.seats == 0 {
+            ("This class is full.", false)
+        } else if enroll {
+            course.seats -= 1;
+            ("This class has been added to your schedule.", true)
+        } else {
+            ("This class is available to enroll in.", true)
+        };
+        results.push((
+            format!(
+                "{} ({}): {message}",
+                course.description, course.class_number
+            ),
+            success,
+        ));
+        if enroll && success {
+            cart.courses.remove(index);
+        }
+    }
+    results
+}
+
+fn login_page(error: bool) -> String {
+    let error = if error {
+        r#"<div id="ptloginerrorcont">Your User ID and/or Password are invalid.</div>"#
+    } else {
+        ""
+    };
+    page(
+        "Sign In",
+        &format!(
+            r#"{error}
+<form method="post" action="/login">
+  <input id="userid" name="userid" type="text">
+  <input id="pwd" name="pwd" type="password">
+  <button type="submit">Sign In</button>
+</form>"#
+        ),
+    )
+}
+
+fn expire_page() -> String {
+    page(
+        "Session Expired",
+        r#"<p>Your session has expired due to inactivity.</p>
+<a href="/login?cmd=login">Sign in to PeopleSoft</a>"#,
+    )
+}
+
+/// Shown once Duo approves, by push or passcode
+const TRUST_PROMPT: &str = r#"<p>Is this your device?</p><button id="trust-browser-button" onclick="location.href='/duo/trust'">Yes, this is my device</button>"#;
+
+fn duo_page(scenario: &Scenario, times_out: bool, cart_path: &str) -> String {
+    let outcome = if times_out {
+        r#"wrapper.classList.add("auth-error");
+    wrapper.innerHTML = '<p>Duo Push timed out.</p><button class="try-again-button" onclick="location.reload()">Try again</button>';"#
+            .to_string()
+    } else {
+        // marked so `benches/wait.rs` can tell how long after insertion the button was seen
+        format!(
+            "wrapper.innerHTML = {};\n    performance.mark(\"duo-outcome\");",
+            serde_json::json!(TRUST_PROMPT)
+        )
+    };
+    page(
+        "Duo",
+        &format!(
+            r#"<div id="auth-view-wrapper"><p>Check for a Duo Push, then come back to {cart_path}.</p></div>
+<a class="other-options-link" href="/duo/options">Other options</a>
+<script>
+  setTimeout(() => {{
+    const wrapper = document.getElementById("auth-view-wrapper");
+    {outcome}
+  }}, {});
+</script>"#,
+            scenario.duo_delay
+        ),
+    )
+}
+
+fn duo_options_page() -> String {
+    page(
+        "Duo",
+        r#"<ul>
+  <li><a data-testid="test-id-passcode" href="/duo/passcode">Duo Mobile passcode</a></li>
+</ul>"#,
+    )
+}
+
+fn passcode_page(error: bool) -> String {
+    let error = if error { "Incorrect passcode." } else { "" };
+    page(
+        "Duo",
+        &format!(
+            r#"<form method="post" action="/duo/passcode">
+  <p role="alert">{error}</p>
+  <input name="passcode-input" type="text">
+  <button class="verify-button" type="submit">Verify</button>
+</form>"#
+        ),
+    )
+}
+
+fn cart_select_page(scenario: &Scenario, cart_path: &str) -> String {
+    let carts: String = scenario
+        .carts
+        .iter()
+        .enumerate()
+        .map(|(index, cart)| {
+            format!(
+                r#"<li><a id="SSR_CART_TRM_FL_TERM_DESCR30${index}" href="{cart_path}?term={index}">{}</a></li>"#,
+                escape(&cart.term)
+            )
+        })
+        .collect();
+    page("Shopping Cart", &format!("<ul>{carts}</ul>"))
+}
+
+fn cart_page(
+    cart: &PracticeCart,
+    term: usize,
+    results: &[(String, bool)],
+    cart_path: &str,
+) -> String {
+    let rows: String = cart
+        .courses
+        .iter()
+        .enumerate()
+        .map(|(index, course)| {
+            let availability = if course.seats > 0 {
+                "Open"
+            } else if course.waitlist {
+                "Wait List"
+            } else {
+                "Closed"
+            };
+            format!(
+                r#"<tr id="SSR_REGFORM_VW$0_row_{index}">
+  <td><input type="checkbox" id="DERIVED_REGFRM1_SSR_SELECT${index}" name="select" value="{}"></td>
+  <td><span id="DERIVED_SSR_FL_SSR_DESCR80${index}">{} ({})</span></td>
+  <td><span id="DERIVED_REGFRM1_SSR_MTG_SCHED_LONG${index}">{}</span></td>
+  <td><span id="DERIVED_REGFRM1_SSR_MTG_LOC_LONG${index}">{}</span></td>
+  <td><span id="DERIVED_REGFRM1_SSR_INSTR_LONG${index}">{}</span></td>
+  <td><span id="DERIVED_SSR_FL_SSR_UNITS_LBL${index}">{}</span></td>
+  <td><span id="DERIVED_SSR_FL_SSR_AVAIL_FL${index}">{availability}</span></td>
+  <td><span id="DERIVED_SSR_FL_SSR_DESCR50${index}">{} of {} seats</span></td>
+</tr>"#,
+                escape(&course.class_number),
+                escape(&course.description),
+                escape(&course.class_number),
+                escape(&course.schedule),
+                escape(&course.room),
+                escape(&course.instructor),
+                escape(&course.credits),
+                course.seats,
+                course.capacity,
+            )
+        })
+        .collect();
+    let window = SITE
+        .rsplit_once('_')
+        .map(|(_, window)| window)
+        .unwrap_or("0");
+    let results: String = results
+        .iter()
+        .enumerate()
+        .map(|(index, (description, success))| {
+            format!(
+                r#"<div id="win{window}div$ICField229_row${index}">
+  <span id="DERIVED_REGFRM1_DESCRLONG${index}">{}</span>
+  <div id="win{window}divDERIVED_REGFRM1_SSR_STATUS_LONG${index}"><img src="{CACHE}/{}"></div>
+</div>"#,
+                escape(description),
+                if *success { SUCCESS_ICON } else { ERROR_ICON },
+            )
+        })
+        .collect();
+    page(
+        &cart.term,
+        &format!(
+            r##"<h1>{}</h1>
+<div id="results">{results}</div>
+<form id="cart" method="post">
+  <table>{rows}</table>
+</form>
+<a id="DERIVED_SSR_FL_SSR_VALIDATE_FL" href="javascript:void(0)" onclick="submitCart('validate')">Validate</a>
+<a id="DERIVED_SSR_FL_SSR_ENROLL_FL" href="javascript:void(0)" onclick="confirmEnroll()">Enroll</a>
+<div id="modal"></div>
+<script>
+  function submitCart(action) {{
+    const form = document.getElementById("cart");
+    form.action = "{cart_path}?term={term}&action=" + action;
+    form.submit();
+  }}
+  function confirmEnroll() {{
+    document.getElementById("modal").innerHTML =
+      '<p>Are you sure you want to enroll?</p><a id="#ICYes" href="javascript:void(0)" onclick="submitCart(\'enroll\')">Yes</a>';
+  }}
+</script>"##,
+            escape(&cart.term)
+        ),
+    )
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{}</title></head><body>\n{body}\n</body></html>",
+        escape(title)
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn parse_form(body: &str) -> Vec<(String, String)> {
+    body.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(name, value)| (decode(name), decode(value)))
+        .collect()
+}
+
+/// Decodes `application/x-www-form-urlencoded` text
+fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => match bytes
+                .get(i + 1..i + 3)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                Some(byte) => {
+                    decoded.push(byte);
+                    i += 2;
+                }
+                None => decoded.push(b'%'),
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn html(body: String) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_data(body).with_header(header("Content-Type", "text/html; charset=utf-8"))
+}
+
+fn redirect(location: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_data(Vec::new())
+        .with_status_code(303)
+        .with_header(header("Location", location))
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("static header is valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cart(opens_in: u64) -> PracticeCart {
+        let mut cart = Scenario::default().carts.remove(0);
+        cart.opens_in = opens_in;
+        cart
+    }
+
+    #[test]
+    fn decodes_forms() {
+        assert_eq!(
+            parse_form("userid=j%2Edoe&pwd=a+b%26c&select=4321&select=5510&empty="),
+            vec![
+                ("userid".to_string(), "j.doe".to_string()),
+                ("pwd".to_string(), "a b&c".to_string()),
+                ("select".to_string(), "4321".to_string()),
+                ("select".to_string(), "5510".to_string()),
+                ("empty".to_string(), String::new()),
+            ]
+        );
+        assert!(parse_form("").is_empty());
+        assert_eq!(decode("100%"), "100%");
+        assert_eq!(decode("%zz%4"), "%zz%4");
+        assert_eq!(decode("%C3%A9"), "é");
+    }
+
+    #[test]
+    fn enrolls_in_courses_with_seats() {
+        let mut cart = cart(0);
+        let results = submit(&mut cart, &["4322", "5510", "9999"], true, Instant::now());
+        assert_eq!(results.len(), 2);
+        assert!(results[0].1 && results[0].0.contains("added to your schedule"));
+        assert!(!results[1].1 && results[1].0.contains("full"));
+        // the enrolled course leaves the cart, the full one stays
+        assert!(!cart
+            .courses
+            .iter()
+            .any(|course| course.class_number == "4322"));
+        assert!(cart
+            .courses
+            .iter()
+            .any(|course| course.class_number == "5510"));
+    }
+
+    #[test]
+    fn validates_without_taking_a_seat() {
+        let mut cart = cart(0);
+        let results = submit(&mut cart, &["4322"], false, Instant::now());
+        assert!(results[0].1 && results[0].0.contains("available to enroll"));
+        let course = cart
+            .courses
+            .iter()
+            .find(|course| course.class_number == "4322")
+            .unwrap();
+        assert_eq!(course.seats, 1);
+    }
+
+    /// Sends one HTTP/1.0 request to `portal` and returns the whole response
+    fn send(portal: &PracticePortal, request: &str) -> String {
+        use std::io::{Read, Write};
+        let mut stream = std::net::TcpStream::connect(("127.0.0.1", portal.port)).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        let _ = stream.read_to_string(&mut response);
+        response
+    }
+
+    #[test]
+    fn keeps_a_login_across_runs_with_its_cookie() {
+        let scenario = Scenario {
+            duo: DuoScenario::None,
+            latency: 0,
+            ..Scenario::default()
+        };
+        let cart_path = "/psc/practice_1/EMPLOYEE/SA/c/SSR_STUDENT_FL.SSR_SHOP_CART_FL.GBL";
+        let portal = PracticePortal::start(scenario.clone()).unwrap();
+        let form = "userid=student&pwd=practice";
+        let login = send(
+            &portal,
+            &format!(
+                "POST /login HTTP/1.0\r\nContent-Length: {}\r\n\r\n{form}",
+                form.len()
+            ),
+        );
+        let cookie = login
+            .lines()
+            .find_map(|line| line.strip_prefix("Set-Cookie: "))
+            .and_then(|cookie| cookie.split(';').next())
+            .unwrap()
+            .to_string();
+
+        // a new portal, like a later run, takes the cookie instead of asking to log in
+        let portal = PracticePortal::start(scenario).unwrap();
+        let fresh = send(&portal, &format!("GET {cart_path} HTTP/1.0\r\n\r\n"));
+        assert!(fresh.contains("Location: /login"), "{fresh}");
+        let resumed = send(
+            &portal,
+            &format!("GET {cart_path} HTTP/1.0\r\nCookie: {cookie}\r\n\r\n"),
+        );
+        assert!(resumed.contains("Spring 2027"), "{resumed}");
+    }
+
+    #[test]
+    fn fails_everything_before_enrollment_opens() {
+        let mut cart = cart(60);
+        let results = submit(&mut cart, &["4321", "6120"], true, Instant::now());
+        assert!(results
+            .iter()
+            .all(|(message, success)| !success && message.contains("not open yet")));
+        assert_eq!(cart.courses.len(), 4);
+    }
+}
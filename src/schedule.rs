@@ -0,0 +1,223 @@
+use async_std::task::{sleep, yield_now};
+use chrono::{DateTime, Local, NaiveDateTime, TimeDelta, TimeZone};
+use chrono_tz::Tz;
+use indicatif::ProgressBar;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::clock::ServerClock;
+use crate::error::SniperError;
+
+/// When to enroll, as given on the command line, in a plan, or at the prompt
+#[derive(Debug, Clone, Copy)]
+pub enum RegistrationTime {
+    /// Wall clock time in the school's timezone, e.g. `2027-01-10 08:00`
+    At(NaiveDateTime),
+    /// Offset like `+5m`, resolved against the clock when it was parsed
+    In(DateTime<Local>),
+}
+
+const FORMATS: [&str; 4] = [
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M",
+];
+
+impl FromStr for RegistrationTime {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if let Some(offset) = value.strip_prefix('+') {
+            return Ok(RegistrationTime::In(Local::now() + parse_offset(offset)?));
+        }
+        FORMATS
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+            .map(RegistrationTime::At)
+            .ok_or_else(|| {
+                format!(
+                    "expected a datetime like 2027-01-10T08:00:00.250 or an offset like +5m, got \"{value}\""
+                )
+            })
+    }
+}
+
+/// Parses offsets like `90s`, `5m`, `1h30m` or `1500ms`
+fn parse_offset(offset: &str) -> Result<TimeDelta, String> {
+    let invalid = || format!("invalid offset \"+{offset}\", expected e.g. +90s, +5m or +1h30m");
+    if offset.is_empty() {
+        return Err(invalid());
+    }
+    let mut total = TimeDelta::zero();
+    let mut rest = offset;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let amount: i64 = rest[..digits].parse().map_err(|_| invalid())?;
+        rest = &rest[digits..];
+        let unit = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let delta = match &rest[..unit] {
+            "ms" => TimeDelta::try_milliseconds(amount),
+            "s" => TimeDelta::try_seconds(amount),
+            "m" => TimeDelta::try_minutes(amount),
+            "h" => TimeDelta::try_hours(amount),
+            "d" => TimeDelta::try_days(amount),
+            _ => None,
+        };
+        total += delta.ok_or_else(invalid)?;
+        rest = &rest[unit..];
+    }
+    Ok(total)
+}
+
+/// Resolves when to enroll, `None` means right now.
+///
+/// Wall clock times are read in `timezone`, or the local timezone when there is none.
+pub fn registration_time(
+    at: Option<RegistrationTime>,
+    timezone: Option<Tz>,
+) -> Result<DateTime<Local>, SniperError> {
+    let registration_time = match at {
+        None => return Ok(Local::now()),
+        Some(RegistrationTime::In(instant)) => instant,
+        Some(RegistrationTime::At(at)) => match timezone {
+            Some(timezone) => timezone
+                .from_local_datetime(&at)
+                .single()
+                .map(|time| time.with_timezone(&Local))
+                .ok_or_else(|| {
+                    SniperError::Input(format!("{at} is ambiguous or skipped in {timezone}"))
+                })?,
+            None => at.and_local_timezone(Local).single().ok_or_else(|| {
+                SniperError::Input(format!(
+                    "{at} is ambiguous or skipped in the local timezone"
+                ))
+            })?,
+        },
+    };
+    if registration_time < Local::now() {
+        Err(SniperError::Input(format!(
+            "registration time {} has already passed",
+            registration_time.format("%Y-%m-%d %H:%M:%S%.3f %:z")
+        )))?
+    }
+    Ok(registration_time)
+}
+
+/// How long before a target `wait_until` stops sleeping and spins instead, enough to cover
+/// the timer's oversleep without pinning a core for long
+const SPIN_MS: i64 = 20;
+
+/// Sleeps until just before `target`, then spins so it returns as close to it as possible
+pub async fn wait_until(target: DateTime<Local>) {
+    loop {
+        let remaining = target - Local::now();
+        if remaining <= TimeDelta::zero() {
+            // the target has passed
+            break;
+        } else if remaining <= TimeDelta::milliseconds(SPIN_MS) {
+            // close enough to spin, but let other snipers run
+            yield_now().await;
+        } else {
+            // sleep in steps of at most 4 seconds so a suspended machine catches up
+            let sleep_for = (remaining - TimeDelta::milliseconds(SPIN_MS))
+                .min(TimeDelta::seconds(4))
+                .to_std()
+                .unwrap_or_default();
+            sleep(sleep_for).await;
+        }
+    }
+}
+
+/// Shows the time left until firing for `target` on `pb`, along with the portal's clock skew
+/// when there is a `clock`
+pub async fn countdown(target: DateTime<Local>, pb: &ProgressBar, clock: Option<&ServerClock>) {
+    loop {
+        let fire_time = clock.map_or(target, |clock| clock.fire_time(target));
+        let remaining = fire_time - Local::now();
+        if remaining <= TimeDelta::zero() {
+            break;
+        }
+        let tenths = remaining.num_milliseconds() / 100;
+        let skew = match clock.map(|clock| clock.skew()) {
+            Some(Some(skew)) => format!(", {skew}"),
+            Some(None) => ", server clock unknown".to_string(),
+            None => String::new(),
+        };
+        pb.set_message(format!(
+            "Registration at {} in {:02}:{:02}:{:02}.{}{skew}",
+            target.format("%Y-%m-%d %H:%M:%S%.3f"),
+            tenths / 36_000,
+            tenths / 600 % 60,
+            tenths / 10 % 60,
+            tenths % 10
+        ));
+        sleep(Duration::from_millis(100)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_offsets() {
+        assert_eq!(parse_offset("90s"), Ok(TimeDelta::seconds(90)));
+        assert_eq!(parse_offset("5m"), Ok(TimeDelta::minutes(5)));
+        assert_eq!(
+            parse_offset("1h30m"),
+            Ok(TimeDelta::hours(1) + TimeDelta::minutes(30))
+        );
+        assert_eq!(parse_offset("1500ms"), Ok(TimeDelta::milliseconds(1500)));
+        assert_eq!(parse_offset("2d"), Ok(TimeDelta::days(2)));
+    }
+
+    #[test]
+    fn rejects_bad_offsets() {
+        for offset in ["", "5", "m", "5x", "1h30", "-5m", "99999999999999999999s"] {
+            assert!(parse_offset(offset).is_err(), "{offset} parsed");
+        }
+    }
+
+    fn at(value: &str) -> Option<RegistrationTime> {
+        Some(value.parse().unwrap())
+    }
+
+    #[test]
+    fn reads_wall_clock_times_in_the_timezone() {
+        let time =
+            registration_time(at("2099-01-10T08:00:00.250"), Some(Tz::America__New_York)).unwrap();
+        assert_eq!(
+            time.with_timezone(&Tz::America__New_York)
+                .format("%Y-%m-%d %H:%M:%S%.3f %Z")
+                .to_string(),
+            "2099-01-10 08:00:00.250 EST"
+        );
+    }
+
+    #[test]
+    fn rejects_ambiguous_and_skipped_times() {
+        // clocks go back at 2:00 on 2030-11-03 and forward at 2:00 on 2030-03-10
+        for value in ["2030-11-03 01:30", "2030-03-10 02:30"] {
+            let error = registration_time(at(value), Some(Tz::America__New_York)).unwrap_err();
+            assert!(
+                error.to_string().contains("ambiguous or skipped"),
+                "{value}: {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_times_that_have_passed() {
+        let error =
+            registration_time(at("2020-01-10 08:00"), Some(Tz::America__New_York)).unwrap_err();
+        assert!(error.to_string().contains("has already passed"), "{error}");
+        assert!(registration_time(at("+1m"), None).is_ok());
+        assert!(registration_time(None, None).is_ok());
+    }
+}
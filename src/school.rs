@@ -0,0 +1,203 @@
+use async_trait::async_trait;
+use chromiumoxide::Page;
+use chrono_tz::Tz;
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::fmt;
+use std::time::Duration;
+
+use crate::elements::{Course, EmoryPageElements, RegistrationResult, ShoppingCart};
+use crate::error::SniperError;
+use crate::transition::Transition;
+
+mod peoplesoft;
+pub use peoplesoft::PeopleSoftConfig;
+
+/// Schools that course-sniper knows how to drive
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum School {
+    /// Emory University
+    Emory,
+    /// Any PeopleSoft Campus Solutions portal, configured with --host, --site and --node
+    #[value(name = "peoplesoft")]
+    PeopleSoft,
+}
+
+impl School {
+    /// Known PeopleSoft deployment for the school, if it has one
+    pub fn preset(&self) -> Option<PeopleSoftConfig> {
+        match self {
+            School::Emory => Some(PeopleSoftConfig::emory()),
+            School::PeopleSoft => None,
+        }
+    }
+
+    /// Timezone the school announces registration times in, `None` uses the local timezone
+    pub fn timezone(&self) -> Option<Tz> {
+        match self {
+            School::Emory => Some(chrono_tz::America::New_York),
+            School::PeopleSoft => None,
+        }
+    }
+
+    pub fn portal(&self, elements: EmoryPageElements) -> Box<dyn SchoolPortal> {
+        match self {
+            School::Emory | School::PeopleSoft => Box::new(elements),
+        }
+    }
+}
+
+pub enum AuthTransition {
+    AuthSuccess,
+    Duo,
+    AuthFail,
+}
+
+pub enum DuoTransition {
+    TimeOut,
+    Trust,
+    Cart,
+    PasscodeRejected,
+}
+
+pub enum CartTransition {
+    In,
+    Select,
+    Expired,
+}
+
+pub enum SessionTransition {
+    Alive,
+    Expired,
+}
+
+/// How the courses in a cart were read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CartScraper {
+    /// One script reading every row in a single round trip
+    Script,
+    /// Element by element over CDP, used when the script comes back incomplete
+    Elements,
+}
+
+impl fmt::Display for CartScraper {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CartScraper::Script => write!(f, "one script"),
+            CartScraper::Elements => write!(f, "element by element"),
+        }
+    }
+}
+
+/// Courses in the loaded cart, along with how they were read and how long it took
+pub struct CartCourses {
+    pub courses: Vec<Course>,
+    pub scraper: CartScraper,
+    pub elapsed: Duration,
+}
+
+/// Time from starting a scripted enrollment to each of its steps
+#[derive(Debug, Clone, Copy)]
+pub struct EnrollTimings {
+    pub selected: Duration,
+    pub enroll_clicked: Duration,
+    /// The confirm click runs just after the script returns, so this is when it was queued
+    pub confirm_queued: Duration,
+}
+
+/// Everything `run` needs from a school's registration portal.
+///
+/// Every method that waits on the page takes `wait_time` in seconds and errors with
+/// `SniperError::SelectorNotFound` once it has passed. Methods used after logging in error with
+/// `SniperError::SessionExpired` as soon as the login or timeout page shows up instead. Pages that can go several ways are
+/// declared as a `Transition` for the flow to race.
+#[async_trait]
+pub trait SchoolPortal: Send + Sync {
+    /// Page the browser opens first, logging in should land back on the shopping cart
+    fn page_url(&self) -> &str;
+
+    /// Urls the flow reads, which have to load even while resources are blocked
+    fn essential_resources(&self) -> Vec<String>;
+
+    /// Fills in and submits the login form, following the sign in link from the timeout page
+    async fn submit_login(
+        &self,
+        page: &Page,
+        username: &str,
+        password: &str,
+        wait_time: u64,
+    ) -> Result<(), SniperError>;
+
+    /// Where the portal can go once credentials are submitted
+    fn login_transition(&self) -> Transition<'_, AuthTransition>;
+
+    /// How the MFA prompt can end: approved, timed out, or skipped
+    fn mfa_transition(&self) -> Transition<'_, DuoTransition>;
+
+    /// Sends the push again from the timed out MFA prompt
+    async fn resend_push(&self, page: &Page, wait_time: u64) -> Result<(), SniperError>;
+
+    /// Picks the passcode factor from the MFA prompt's other options and submits `passcode`,
+    /// or submits it straight away when the passcode form is already showing
+    async fn submit_passcode(
+        &self,
+        page: &Page,
+        passcode: &str,
+        wait_time: u64,
+    ) -> Result<(), SniperError>;
+
+    /// How a submitted passcode can end: accepted, rejected, or timed out
+    fn passcode_transition(&self) -> Transition<'_, DuoTransition>;
+
+    /// Either the shopping cart list, a single open cart, or the login page again
+    fn cart_transition(&self) -> Transition<'_, CartTransition>;
+
+    /// Whether reloading the portal shows the cart or sends it back to the login page
+    fn session_transition(&self) -> Transition<'_, SessionTransition>;
+
+    async fn get_shopping_carts(&self, page: &Page) -> Result<Vec<ShoppingCart>, SniperError>;
+
+    /// Reads every course in the currently loaded cart
+    async fn get_cart_courses(
+        &self,
+        page: &Page,
+        wait_time: u64,
+    ) -> Result<CartCourses, SniperError>;
+
+    /// Reads the currently loaded cart both ways and how long each took, the script's time is
+    /// `None` when it came back incomplete
+    async fn time_cart_scrapers(
+        &self,
+        page: &Page,
+    ) -> Result<(Option<Duration>, Duration), SniperError>;
+
+    /// Checks the boxes of the given courses in the currently loaded cart
+    async fn select_courses(
+        &self,
+        page: &Page,
+        courses: &[Course],
+        wait_time: u64,
+    ) -> Result<(), SniperError>;
+
+    /// Checks the boxes of the given courses, clicks enroll and confirms with a single script, so
+    /// only one call to the browser sits between the reload and the enrollment request
+    async fn scripted_enroll(
+        &self,
+        page: &Page,
+        courses: &[Course],
+        wait_time: u64,
+    ) -> Result<EnrollTimings, SniperError>;
+
+    async fn validate(&self, page: &Page, wait_time: u64) -> Result<(), SniperError>;
+
+    async fn enroll(&self, page: &Page, wait_time: u64) -> Result<(), SniperError>;
+
+    async fn confirm_enroll(&self, page: &Page, wait_time: u64) -> Result<(), SniperError>;
+
+    async fn get_registration_results(
+        &self,
+        page: &Page,
+        wait_time: u64,
+    ) -> Result<Vec<RegistrationResult>, SniperError>;
+}
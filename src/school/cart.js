@@ -0,0 +1,16 @@
+// Reads every cart row in one round trip, with the same innerText the element by element path
+// reads and null wherever a row is missing a field.
+(selectors) =>
+  Array.from(document.querySelectorAll(selectors.course_row), (row) => {
+    const text = (selector) => row.querySelector(selector)?.innerText ?? null;
+    return {
+      class_number: text(selectors.class_number),
+      description: text(selectors.description),
+      seats: text(selectors.seats),
+      availability: text(selectors.availability),
+      schedule: text(selectors.schedule),
+      instructor: text(selectors.instructor),
+      room: text(selectors.room),
+      credits: text(selectors.credits),
+    };
+  })
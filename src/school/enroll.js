@@ -0,0 +1,80 @@
+// Checks the target rows, clicks Enroll and then queues the confirmation in one go, timing each
+// step in milliseconds from the start. Clicking runs PeopleSoft's own handlers, as a user's click would.
+// `waitFor` is wait.js, `targets` are { class_number, description } like `Course::is_row`.
+async (waitFor, selectors, targets, timeout) => {
+  const start = performance.now();
+  // 0 once `selector` shows up, above 0 when the portal logged out instead, below 0 on timeout
+  const waitStep = (selector) =>
+    waitFor([selector, selectors.username_input, selectors.session_timeout], timeout);
+
+  const rowsFound = await waitStep(selectors.course_row);
+  if (rowsFound > 0) {
+    return { outcome: "expired", step: "select" };
+  }
+  if (rowsFound < 0) {
+    return { outcome: "timeout", selector: selectors.course_row, step: "select" };
+  }
+  const classNumber = (row) => {
+    const text = row.querySelector(selectors.class_number)?.innerText ?? "";
+    return text.match(/\(\s*(\d+)\s*\)/)?.[1] ?? null;
+  };
+  const rows = Array.from(document.querySelectorAll(selectors.course_row), (row) => ({
+    row,
+    class_number: classNumber(row),
+    description: row.querySelector(selectors.description)?.innerText ?? "None",
+  }));
+  const checkboxes = [];
+  for (const target of targets) {
+    const found = rows.find((row) =>
+      target.class_number !== null && row.class_number !== null
+        ? target.class_number === row.class_number
+        : target.description === row.description
+    );
+    const checkbox = found?.row.querySelector(selectors.checkboxes);
+    if (!checkbox) {
+      return { outcome: "missing", course: target.description };
+    }
+    checkboxes.push(checkbox);
+  }
+  for (const checkbox of checkboxes) {
+    if (!checkbox.checked) {
+      checkbox.click();
+    }
+  }
+  const selected = performance.now() - start;
+
+  const enrollFound = await waitStep(selectors.enroll_button);
+  if (enrollFound > 0) {
+    return { outcome: "expired", step: "enroll" };
+  }
+  if (enrollFound < 0) {
+    return { outcome: "timeout", selector: selectors.enroll_button, step: "enroll" };
+  }
+  document.querySelector(selectors.enroll_button).click();
+  const enrollClicked = performance.now() - start;
+
+  const confirmFound = await waitStep(selectors.enroll_confirm_button);
+  if (confirmFound > 0) {
+    return { outcome: "expired", step: "confirm" };
+  }
+  if (confirmFound < 0) {
+    return {
+      outcome: "timeout",
+      selector: selectors.enroll_confirm_button,
+      step: "confirm",
+      selected,
+      enroll_clicked: enrollClicked,
+    };
+  }
+  const confirm = document.querySelector(selectors.enroll_confirm_button);
+  // confirming can navigate away, so click after this script has returned, which also means
+  // the click can only be timed as queued
+  const confirmQueued = performance.now() - start;
+  setTimeout(() => confirm.click(), 0);
+  return {
+    outcome: "submitted",
+    selected,
+    enroll_clicked: enrollClicked,
+    confirm_queued: confirmQueued,
+  };
+}
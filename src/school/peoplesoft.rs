@@ -0,0 +1,780 @@
+use async_trait::async_trait;
+use chromiumoxide::{error::CdpError, Element, Page};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use super::{
+    AuthTransition, CartCourses, CartScraper, CartTransition, DuoTransition, EnrollTimings,
+    SchoolPortal, SessionTransition,
+};
+use crate::elements::{
+    Course, CourseStatus, EmoryPageElements, RegistrationResult, RegistrationStatus, ShoppingCart,
+};
+use crate::error::{Phase, SniperError};
+use crate::transition::{Outcome, Transition};
+use crate::wait::{wait_element, wait_for_any, WAIT_SCRIPT};
+
+/// Reads every row of the cart in one round trip, see `read_cart_elements` for the slow path
+const CART_SCRIPT: &str = include_str!("cart.js");
+/// Selects, enrolls and confirms in one round trip, see `select_courses` for the click path
+const ENROLL_SCRIPT: &str = include_str!("enroll.js");
+/// Longest `enroll.js` waits on each step, three of these stay under the CDP request timeout
+const SCRIPTED_WAIT: Duration = Duration::from_secs(8);
+
+/// Where a PeopleSoft Campus Solutions deployment lives.
+///
+/// The Fluid shopping cart ids are stock PeopleSoft, so these are enough to build every selector.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeopleSoftConfig {
+    /// Host serving the portal, e.g. `saprod.emory.edu`, https is assumed unless a scheme is given
+    pub host: String,
+    /// Site name from the `/psc/<site>/` part of the url, e.g. `saprod_48`
+    pub site: String,
+    /// Portal from the url, almost always `EMPLOYEE`
+    #[serde(default = "default_portal")]
+    pub portal: String,
+    /// Node from the url, e.g. `SA`
+    pub node: String,
+}
+
+fn default_portal() -> String {
+    "EMPLOYEE".to_string()
+}
+
+impl Default for PeopleSoftConfig {
+    fn default() -> Self {
+        Self {
+            host: String::new(),
+            site: String::new(),
+            portal: default_portal(),
+            node: String::new(),
+        }
+    }
+}
+
+impl PeopleSoftConfig {
+    pub fn emory() -> Self {
+        Self {
+            host: "saprod.emory.edu".to_string(),
+            site: "saprod_48".to_string(),
+            portal: default_portal(),
+            node: "SA".to_string(),
+        }
+    }
+
+    fn base_url(&self) -> String {
+        if self.host.contains("://") {
+            self.host.trim_end_matches('/').to_string()
+        } else {
+            format!("https://{}", self.host.trim_end_matches('/'))
+        }
+    }
+
+    /// Splits `saprod_48` into the cache site `saprod` and window number `48`
+    fn site_parts(&self) -> (&str, &str) {
+        match self.site.rsplit_once('_') {
+            Some((site, window)) if window.parse::<u32>().is_ok() => (site, window),
+            _ => (&self.site, "0"),
+        }
+    }
+
+    pub fn elements(&self) -> EmoryPageElements {
+        let base_url = self.base_url();
+        let (cache_site, window) = self.site_parts();
+        EmoryPageElements {
+            page_url: format!(
+                "{base_url}/psc/{}/{}/{}/c/SSR_STUDENT_FL.SSR_SHOP_CART_FL.GBL",
+                self.site, self.portal, self.node
+            ),
+            username_input: "input#userid".to_string(),
+            passwd_input: "input#pwd".to_string(),
+            login_error: "div#ptloginerrorcont".to_string(),
+            session_timeout: r#"a[href*="cmd=login"]"#.to_string(),
+            validate_button: "a#DERIVED_SSR_FL_SSR_VALIDATE_FL".to_string(),
+            enroll_button: "a#DERIVED_SSR_FL_SSR_ENROLL_FL".to_string(),
+            enroll_confirm_button: r#"a[id="\#ICYes"]"#.to_string(),
+            semester_cart: r#"a[id^="SSR_CART_TRM_FL_TERM_DESCR30$"]"#.to_string(),
+            course_row: r#"tr[id^="SSR_REGFORM_VW$0_row_"]"#.to_string(),
+            class_number: r#"span[id^="DERIVED_SSR_FL_SSR_DESCR80$"]"#.to_string(),
+            checkboxes: r#"input[type="checkbox"][id^="DERIVED_REGFRM1_SSR_SELECT$"]"#.to_string(),
+            availability: r#"span[id^="DERIVED_SSR_FL_SSR_AVAIL_FL$"]"#.to_string(),
+            description: r#"span[id^="DERIVED_SSR_FL_SSR_DESCR80$"]"#.to_string(),
+            schedule: r#"span[id^="DERIVED_REGFRM1_SSR_MTG_SCHED_LONG$"]"#.to_string(),
+            room: r#"span[id^="DERIVED_REGFRM1_SSR_MTG_LOC_LONG$"]"#.to_string(),
+            instructor: r#"span[id^="DERIVED_REGFRM1_SSR_INSTR_LONG$"]"#.to_string(),
+            credits: r#"span[id^="DERIVED_SSR_FL_SSR_UNITS_LBL$"]"#.to_string(),
+            seats: r#"span[id^="DERIVED_SSR_FL_SSR_DESCR50$"]"#.to_string(),
+            results_rows: format!(r#"div[id^="win{window}div$ICField229_row$"]"#),
+            result_description: r#"span[id^="DERIVED_REGFRM1_DESCRLONG$"]"#.to_string(),
+            result_status: format!(r#"div[id^="win{window}divDERIVED_REGFRM1_SSR_STATUS_LONG$"]"#),
+            registration_success: format!("/cs/{cache_site}/cache/PS_CS_STATUS_SUCCESS_ICN_1.gif"),
+            registration_fail: format!("/cs/{cache_site}/cache/PS_CS_STATUS_ERROR_ICN_1.gif"),
+            duo_waiting: "div#auth-view-wrapper:not(.auth-error)".to_string(),
+            duo_trust_browser: r#"button[id="trust-browser-button"]"#.to_string(),
+            duo_time_out_try_again: r#"button.try-again-button"#.to_string(),
+            duo_other_options: "a.other-options-link".to_string(),
+            duo_passcode_option: r#"[data-testid="test-id-passcode"]"#.to_string(),
+            duo_passcode_input: r#"input[name="passcode-input"]"#.to_string(),
+            duo_passcode_submit: "button.verify-button".to_string(),
+            duo_passcode_error: r#"[role="alert"]:not(:empty)"#.to_string(),
+        }
+    }
+}
+
+/// Reads the class number from the first number in parentheses, e.g. `CS 170-1 (4321)`
+fn parse_class_number(text: &str) -> Option<String> {
+    text.split('(')
+        .skip(1)
+        .filter_map(|part| part.split_once(')'))
+        .map(|(inside, _)| inside.trim())
+        .find(|inside| !inside.is_empty() && inside.chars().all(|c| c.is_ascii_digit()))
+        .map(|inside| inside.to_string())
+}
+
+/// What `enroll.js` came back with, times are milliseconds from the script starting
+#[derive(Deserialize)]
+#[serde(tag = "outcome", rename_all = "lowercase")]
+enum ScriptedEnroll {
+    Submitted {
+        selected: f64,
+        enroll_clicked: f64,
+        confirm_queued: f64,
+    },
+    Missing {
+        course: String,
+    },
+    Timeout {
+        selector: String,
+        step: ScriptedStep,
+        /// Only filled in once the step has passed
+        #[serde(default)]
+        selected: f64,
+        #[serde(default)]
+        enroll_clicked: f64,
+    },
+    /// The portal showed the login or timeout page while waiting on `step`
+    Expired {
+        step: ScriptedStep,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ScriptedStep {
+    Select,
+    Enroll,
+    Confirm,
+}
+
+impl ScriptedStep {
+    /// Phase to report a failed step under, the confirm step is reached from enroll
+    fn phase(&self) -> Phase {
+        match self {
+            ScriptedStep::Select => Phase::Select,
+            ScriptedStep::Enroll | ScriptedStep::Confirm => Phase::Enroll,
+        }
+    }
+}
+
+/// Text of every field of one cart row as read by `cart.js`
+#[derive(Deserialize)]
+struct CartRow {
+    class_number: Option<String>,
+    description: Option<String>,
+    seats: Option<String>,
+    availability: Option<String>,
+    schedule: Option<String>,
+    instructor: Option<String>,
+    room: Option<String>,
+    credits: Option<String>,
+}
+
+impl CartRow {
+    /// `None` when the row is missing a field the element by element path would error on
+    fn into_course(self) -> Option<Course> {
+        Some(Course {
+            class_number: self.class_number.as_deref().and_then(parse_class_number),
+            availability: course_status(&self.seats?, &self.availability?),
+            description: self.description?,
+            schedule: self.schedule?,
+            room: self.room?,
+            instructor: self.instructor?,
+            credits: self.credits?,
+        })
+    }
+}
+
+/// Reads availability from the seats text, e.g. `5 of 30`, and the availability text
+fn course_status(seats: &str, availability: &str) -> CourseStatus {
+    let nums: Vec<u32> = seats
+        .split_whitespace()
+        .filter_map(|word| word.parse().ok())
+        .collect();
+    match availability {
+        text if text.contains("Wait List") => {
+            if nums.len() == 2 {
+                // a first number past the second is no position, so it is unknown like a missing one
+                CourseStatus::Waitlist {
+                    position: nums[1].checked_sub(nums[0]).unwrap_or(999),
+                }
+            } else {
+                CourseStatus::Waitlist { position: 999 }
+            }
+        }
+        text if text.contains("Closed") => CourseStatus::Closed,
+        text if text.contains("Open") => {
+            if nums.len() == 2 {
+                CourseStatus::Open {
+                    available: nums[0],
+                    capacity: nums[1],
+                }
+            } else {
+                CourseStatus::Open {
+                    available: 0,
+                    capacity: 0,
+                }
+            }
+        }
+        _ => CourseStatus::Closed,
+    }
+}
+
+impl EmoryPageElements {
+    /// Waits for `selector` like `wait_element`, but errors with `SessionExpired` as soon as the
+    /// portal shows the login or timeout page instead
+    async fn wait_signed_in(
+        &self,
+        page: &Page,
+        selector: &str,
+        wait_time: u64,
+        phase: Phase,
+    ) -> Result<Element, SniperError> {
+        let found = wait_for_any(
+            page,
+            &[selector, &self.username_input, &self.session_timeout],
+            wait_time,
+            phase,
+        )
+        .await
+        .map_err(|e| match e {
+            SniperError::SelectorNotFound { phase, .. } => SniperError::SelectorNotFound {
+                selector: selector.to_string(),
+                phase,
+            },
+            e => e,
+        })?;
+        if found > 0 {
+            return Err(SniperError::SessionExpired(phase));
+        }
+        Ok(page.find_element(selector).await?)
+    }
+
+    /// Class number and description of a cart row, enough to tell which course it is
+    async fn row_identity(&self, row: &Element) -> Result<(Option<String>, String), CdpError> {
+        let class_number = match row.find_element(&self.class_number).await {
+            Ok(element) => element
+                .inner_text()
+                .await?
+                .as_deref()
+                .and_then(parse_class_number),
+            Err(_) => None,
+        };
+        let description = row
+            .find_element(&self.description)
+            .await?
+            .inner_text()
+            .await?
+            .unwrap_or("None".to_string());
+        Ok((class_number, description))
+    }
+
+    /// Reads the whole cart with one script, `None` if the script failed or a row came back
+    /// incomplete
+    async fn scrape_cart(&self, page: &Page) -> Option<Vec<Course>> {
+        let selectors = serde_json::to_string(self).ok()?;
+        let rows: Vec<CartRow> = page
+            .evaluate_expression(format!("({CART_SCRIPT})({selectors})"))
+            .await
+            .ok()?
+            .into_value()
+            .ok()?;
+        if rows.is_empty() {
+            return None;
+        }
+        rows.into_iter().map(CartRow::into_course).collect()
+    }
+
+    /// Reads the cart one element at a time, several round trips per row
+    async fn read_cart_elements(&self, page: &Page) -> Result<Vec<Course>, CdpError> {
+        let course_row_elements = page.find_elements(&self.course_row).await?;
+        futures::future::try_join_all(course_row_elements.into_iter().map(|row| async move {
+            let seats = row
+                .find_element(&self.seats)
+                .await?
+                .inner_text()
+                .await?
+                .unwrap_or("".to_string());
+            let availability = row
+                .find_element(&self.availability)
+                .await?
+                .inner_text()
+                .await?
+                .unwrap_or("".to_string());
+
+            let (class_number, description) = self.row_identity(&row).await?;
+            Ok::<Course, CdpError>(Course {
+                class_number,
+                availability: course_status(&seats, &availability),
+                description,
+                schedule: row
+                    .find_element(&self.schedule)
+                    .await?
+                    .inner_text()
+                    .await?
+                    .unwrap_or("None".to_string()),
+                instructor: row
+                    .find_element(&self.instructor)
+                    .await?
+                    .inner_text()
+                    .await?
+                    .unwrap_or("None".to_string()),
+                room: row
+                    .find_element(&self.room)
+                    .await?
+                    .inner_text()
+                    .await?
+                    .unwrap_or("None".to_string()),
+                credits: row
+                    .find_element(&self.credits)
+                    .await?
+                    .inner_text()
+                    .await?
+                    .unwrap_or("None".to_string()),
+            })
+        }))
+        .await
+    }
+}
+
+#[async_trait]
+impl SchoolPortal for EmoryPageElements {
+    fn page_url(&self) -> &str {
+        &self.page_url
+    }
+
+    fn essential_resources(&self) -> Vec<String> {
+        vec![
+            self.registration_success.clone(),
+            self.registration_fail.clone(),
+        ]
+    }
+
+    async fn submit_login(
+        &self,
+        page: &Page,
+        username: &str,
+        password: &str,
+        wait_time: u64,
+    ) -> Result<(), SniperError> {
+        // an expired session lands on a page linking back to the sign in form
+        let found = wait_for_any(
+            page,
+            &[&self.username_input, &self.session_timeout],
+            wait_time,
+            Phase::Login,
+        )
+        .await?;
+        if found == 1 {
+            page.find_element(&self.session_timeout)
+                .await?
+                .click()
+                .await?;
+        }
+        wait_element(page, &self.username_input, wait_time, Phase::Login)
+            .await?
+            .click()
+            .await?
+            .type_str(username)
+            .await?;
+        page.find_element(&self.passwd_input)
+            .await?
+            .click()
+            .await?
+            .type_str(password)
+            .await?
+            .press_key("Enter")
+            .await?;
+        Ok(())
+    }
+
+    fn login_transition(&self) -> Transition<'_, AuthTransition> {
+        Transition::new(
+            Phase::Login,
+            vec![
+                Outcome::new(&self.login_error, AuthTransition::AuthFail),
+                Outcome::new(&self.duo_waiting, AuthTransition::Duo),
+                Outcome::new(&self.semester_cart, AuthTransition::AuthSuccess),
+                Outcome::new(&self.course_row, AuthTransition::AuthSuccess),
+            ],
+        )
+    }
+
+    fn mfa_transition(&self) -> Transition<'_, DuoTransition> {
+        Transition::new(
+            Phase::Duo,
+            vec![
+                Outcome::click(&self.duo_trust_browser, DuoTransition::Trust),
+                Outcome::new(&self.duo_time_out_try_again, DuoTransition::TimeOut),
+                Outcome::new(&self.semester_cart, DuoTransition::Cart),
+                Outcome::new(&self.course_row, DuoTransition::Cart),
+            ],
+        )
+    }
+
+    async fn resend_push(&self, page: &Page, wait_time: u64) -> Result<(), SniperError> {
+        wait_element(page, &self.duo_time_out_try_again, wait_time, Phase::Duo)
+            .await?
+            .click()
+            .await?;
+        Ok(())
+    }
+
+    async fn submit_passcode(
+        &self,
+        page: &Page,
+        passcode: &str,
+        wait_time: u64,
+    ) -> Result<(), SniperError> {
+        let found = wait_for_any(
+            page,
+            &[&self.duo_passcode_input, &self.duo_other_options],
+            wait_time,
+            Phase::Duo,
+        )
+        .await?;
+        if found == 1 {
+            page.find_element(&self.duo_other_options)
+                .await?
+                .click()
+                .await?;
+            wait_element(page, &self.duo_passcode_option, wait_time, Phase::Duo)
+                .await?
+                .click()
+                .await?;
+        }
+        let input = wait_element(page, &self.duo_passcode_input, wait_time, Phase::Duo).await?;
+        // a rejected passcode can be left in the field
+        input
+            .call_js_fn("function() { this.value = ''; }", false)
+            .await?;
+        input.click().await?.type_str(passcode).await?;
+        page.find_element(&self.duo_passcode_submit)
+            .await?
+            .click()
+            .await?;
+        Ok(())
+    }
+
+    fn passcode_transition(&self) -> Transition<'_, DuoTransition> {
+        Transition::new(
+            Phase::Duo,
+            vec![
+                Outcome::click(&self.duo_trust_browser, DuoTransition::Trust),
+                Outcome::new(&self.duo_passcode_error, DuoTransition::PasscodeRejected),
+                Outcome::new(&self.duo_time_out_try_again, DuoTransition::TimeOut),
+                Outcome::new(&self.semester_cart, DuoTransition::Cart),
+                Outcome::new(&self.course_row, DuoTransition::Cart),
+            ],
+        )
+    }
+
+    fn cart_transition(&self) -> Transition<'_, CartTransition> {
+        Transition::new(
+            Phase::Cart,
+            vec![
+                Outcome::new(&self.semester_cart, CartTransition::Select),
+                Outcome::new(&self.course_row, CartTransition::In),
+                Outcome::new(&self.username_input, CartTransition::Expired),
+                Outcome::new(&self.session_timeout, CartTransition::Expired),
+            ],
+        )
+    }
+
+    fn session_transition(&self) -> Transition<'_, SessionTransition> {
+        Transition::new(
+            Phase::Session,
+            vec![
+                Outcome::new(&self.username_input, SessionTransition::Expired),
+                Outcome::new(&self.session_timeout, SessionTransition::Expired),
+                Outcome::new(&self.semester_cart, SessionTransition::Alive),
+                Outcome::new(&self.course_row, SessionTransition::Alive),
+            ],
+        )
+    }
+
+    async fn get_shopping_carts(&self, page: &Page) -> Result<Vec<ShoppingCart>, SniperError> {
+        let semester_cart_elements = page.find_elements(&self.semester_cart).await?;
+        let semester_carts = futures::future::try_join_all(semester_cart_elements.into_iter().map(
+            |cart| async move {
+                let text = cart.inner_text().await?.unwrap_or_default();
+                Ok::<_, SniperError>(ShoppingCart {
+                    element: cart,
+                    text,
+                })
+            },
+        ))
+        .await?;
+        Ok(semester_carts)
+    }
+
+    async fn get_cart_courses(
+        &self,
+        page: &Page,
+        wait_time: u64,
+    ) -> Result<CartCourses, SniperError> {
+        self.wait_signed_in(page, &self.course_row, wait_time, Phase::Courses)
+            .await?;
+        let start = Instant::now();
+        let (courses, scraper) = match self.scrape_cart(page).await {
+            Some(courses) => (courses, CartScraper::Script),
+            None => (self.read_cart_elements(page).await?, CartScraper::Elements),
+        };
+        Ok(CartCourses {
+            courses,
+            scraper,
+            elapsed: start.elapsed(),
+        })
+    }
+
+    async fn time_cart_scrapers(
+        &self,
+        page: &Page,
+    ) -> Result<(Option<Duration>, Duration), SniperError> {
+        let start = Instant::now();
+        let script = self.scrape_cart(page).await.map(|_| start.elapsed());
+        let start = Instant::now();
+        self.read_cart_elements(page).await?;
+        Ok((script, start.elapsed()))
+    }
+
+    async fn select_courses(
+        &self,
+        page: &Page,
+        courses: &[Course],
+        wait_time: u64,
+    ) -> Result<(), SniperError> {
+        // rows can move or disappear between reloads, so find each course's row again
+        self.wait_signed_in(page, &self.course_row, wait_time, Phase::Select)
+            .await?;
+        let rows = page.find_elements(&self.course_row).await?;
+        let identities =
+            futures::future::try_join_all(rows.iter().map(|row| self.row_identity(row))).await?;
+        let mut selected = Vec::new();
+        for course in courses {
+            let position = identities
+                .iter()
+                .position(|(class_number, description)| {
+                    course.is_row(class_number.as_deref(), description)
+                })
+                .ok_or_else(|| {
+                    SniperError::CourseMissing(format!(
+                        "{course} is no longer in the shopping cart, nothing was selected"
+                    ))
+                })?;
+            selected.push(&rows[position]);
+        }
+        for row in selected {
+            // a box still ticked from an earlier round would be unticked by another click
+            let checkbox = row.find_element(&self.checkboxes).await?;
+            let checked = checkbox.property("checked").await?;
+            if checked != Some(serde_json::Value::Bool(true)) {
+                checkbox.click().await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn scripted_enroll(
+        &self,
+        page: &Page,
+        courses: &[Course],
+        wait_time: u64,
+    ) -> Result<EnrollTimings, SniperError> {
+        let selectors = serde_json::to_string(self).map_err(CdpError::from)?;
+        let targets = serde_json::to_string(
+            &courses
+                .iter()
+                .map(|course| {
+                    serde_json::json!({
+                        "class_number": course.class_number,
+                        "description": course.description,
+                    })
+                })
+                .collect::<Vec<_>>(),
+        )
+        .map_err(CdpError::from)?;
+        let timeout = Duration::from_secs(wait_time)
+            .min(SCRIPTED_WAIT)
+            .as_millis();
+        let start = Instant::now();
+        let outcome: ScriptedEnroll = page
+            .evaluate_expression(format!(
+                "({ENROLL_SCRIPT})({WAIT_SCRIPT}, {selectors}, {targets}, {timeout})"
+            ))
+            .await?
+            .into_value()
+            .map_err(CdpError::from)?;
+        let millis = |ms: f64| Duration::from_secs_f64(ms.max(0.0) / 1000.0);
+        match outcome {
+            ScriptedEnroll::Submitted {
+                selected,
+                enroll_clicked,
+                confirm_queued,
+            } => Ok(EnrollTimings {
+                selected: millis(selected),
+                enroll_clicked: millis(enroll_clicked),
+                confirm_queued: millis(confirm_queued),
+            }),
+            ScriptedEnroll::Missing { course } => Err(SniperError::CourseMissing(format!(
+                "{course} is no longer in the shopping cart, nothing was selected"
+            ))),
+            // the confirmation can take a while to come back, so keep waiting the usual way
+            ScriptedEnroll::Timeout {
+                step: ScriptedStep::Confirm,
+                selected,
+                enroll_clicked,
+                ..
+            } => {
+                self.confirm_enroll(page, wait_time).await?;
+                Ok(EnrollTimings {
+                    selected: millis(selected),
+                    enroll_clicked: millis(enroll_clicked),
+                    confirm_queued: start.elapsed(),
+                })
+            }
+            ScriptedEnroll::Timeout { selector, step, .. } => Err(SniperError::SelectorNotFound {
+                selector,
+                phase: step.phase(),
+            }),
+            ScriptedEnroll::Expired { step } => Err(SniperError::SessionExpired(step.phase())),
+        }
+    }
+
+    async fn validate(&self, page: &Page, wait_time: u64) -> Result<(), SniperError> {
+        self.wait_signed_in(page, &self.validate_button, wait_time, Phase::Validate)
+            .await?
+            .click()
+            .await?;
+        Ok(())
+    }
+
+    async fn enroll(&self, page: &Page, wait_time: u64) -> Result<(), SniperError> {
+        self.wait_signed_in(page, &self.enroll_button, wait_time, Phase::Enroll)
+            .await?
+            .click()
+            .await?;
+        Ok(())
+    }
+
+    async fn confirm_enroll(&self, page: &Page, wait_time: u64) -> Result<(), SniperError> {
+        self.wait_signed_in(page, &self.enroll_confirm_button, wait_time, Phase::Confirm)
+            .await?
+            .click()
+            .await?;
+        Ok(())
+    }
+
+    async fn get_registration_results(
+        &self,
+        page: &Page,
+        wait_time: u64,
+    ) -> Result<Vec<RegistrationResult>, SniperError> {
+        self.wait_signed_in(page, &self.results_rows, wait_time, Phase::Results)
+            .await?;
+        let result_elements = page.find_elements(&self.results_rows).await?;
+        let results: Vec<RegistrationResult> =
+            futures::future::try_join_all(result_elements.into_iter().map(|result| async move {
+                let status_html = result
+                    .find_element(&self.result_status)
+                    .await?
+                    .inner_html()
+                    .await?
+                    .unwrap_or("".to_string());
+                Ok::<RegistrationResult, CdpError>(RegistrationResult {
+                    description: result
+                        .find_element(&self.result_description)
+                        .await?
+                        .inner_text()
+                        .await?
+                        .unwrap_or("None".to_string()),
+                    status: if status_html.contains(&self.registration_success) {
+                        RegistrationStatus::Success
+                    } else if status_html.contains(&self.registration_fail) {
+                        RegistrationStatus::Fail
+                    } else {
+                        RegistrationStatus::Unknown
+                    },
+                })
+            }))
+            .await?;
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_class_numbers() {
+        assert_eq!(
+            parse_class_number("CS 170-1 (4321)"),
+            Some("4321".to_string())
+        );
+        assert_eq!(
+            parse_class_number("CS 170-1 (Lecture) ( 4321 )"),
+            Some("4321".to_string())
+        );
+        assert_eq!(parse_class_number("CS 170-1 (Lecture)"), None);
+        assert_eq!(parse_class_number("CS 170-1 ()"), None);
+        assert_eq!(parse_class_number("CS 170-1"), None);
+    }
+
+    #[test]
+    fn reads_course_status() {
+        assert!(matches!(
+            course_status("5 of 30 seats", "Open"),
+            CourseStatus::Open {
+                available: 5,
+                capacity: 30
+            }
+        ));
+        assert!(matches!(
+            course_status("", "Open"),
+            CourseStatus::Open {
+                available: 0,
+                capacity: 0
+            }
+        ));
+        assert!(matches!(
+            course_status("3 of 10", "Wait List"),
+            CourseStatus::Waitlist { position: 7 }
+        ));
+        assert!(matches!(
+            course_status("", "Wait List"),
+            CourseStatus::Waitlist { position: 999 }
+        ));
+        assert!(matches!(
+            course_status("12 of 10", "Wait List"),
+            CourseStatus::Waitlist { position: 999 }
+        ));
+        assert!(matches!(
+            course_status("0 of 35", "Closed"),
+            CourseStatus::Closed
+        ));
+        assert!(matches!(
+            course_status("", "Cancelled"),
+            CourseStatus::Closed
+        ));
+    }
+}
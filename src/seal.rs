@@ -0,0 +1,81 @@
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+
+/// Start of every sealed file, so anything else is turned away before deriving a key
+const MAGIC: &[u8] = b"course-sniper sealed v1\n";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// Stretches `passphrase` into a key with Argon2id, slow on purpose to make guessing expensive
+fn derive_key(passphrase: &str, salt: &[u8]) -> Key {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .expect("the salt and key lengths are fixed and valid for Argon2");
+    key
+}
+
+/// Encrypts `plaintext` with a key derived from `passphrase` and a fresh random salt
+pub fn seal(plaintext: &[u8], passphrase: &str) -> Vec<u8> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = XChaCha20Poly1305::new(&derive_key(passphrase, &salt))
+        .encrypt(&nonce, plaintext)
+        .expect("encrypting into a Vec does not fail");
+    [MAGIC, &salt, &nonce, &ciphertext].concat()
+}
+
+/// Decrypts what `seal` wrote, `None` when the passphrase is wrong or the data was changed
+pub fn open(sealed: &[u8], passphrase: &str) -> Option<Vec<u8>> {
+    let rest = sealed.strip_prefix(MAGIC)?;
+    if rest.len() < SALT_LEN + NONCE_LEN {
+        return None;
+    }
+    let (salt, rest) = rest.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    XChaCha20Poly1305::new(&derive_key(passphrase, salt))
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_what_it_sealed() {
+        let sealed = seal(b"cookies", "passphrase");
+        assert!(sealed.starts_with(MAGIC));
+        assert!(!sealed.windows(7).any(|window| window == b"cookies"));
+        assert_eq!(open(&sealed, "passphrase").unwrap(), b"cookies");
+        // a fresh salt and nonce every time
+        assert_ne!(seal(b"cookies", "passphrase"), sealed);
+    }
+
+    #[test]
+    fn turns_away_a_wrong_passphrase() {
+        let sealed = seal(b"cookies", "passphrase");
+        assert!(open(&sealed, "Passphrase").is_none());
+        assert!(open(&sealed, "").is_none());
+    }
+
+    #[test]
+    fn turns_away_tampered_input() {
+        let sealed = seal(b"cookies", "passphrase");
+        // a byte of the salt, the nonce, the ciphertext and the tag
+        let salt = MAGIC.len();
+        let nonce = salt + SALT_LEN;
+        let ciphertext = nonce + NONCE_LEN;
+        for i in [salt, nonce, ciphertext, sealed.len() - 1] {
+            let mut tampered = sealed.clone();
+            tampered[i] ^= 1;
+            assert!(open(&tampered, "passphrase").is_none(), "byte {i}");
+        }
+        assert!(open(&sealed[..sealed.len() - 1], "passphrase").is_none());
+        assert!(open(&sealed[..MAGIC.len() + SALT_LEN], "passphrase").is_none());
+        assert!(open(b"not a sealed file", "passphrase").is_none());
+    }
+}
@@ -0,0 +1,43 @@
+use std::sync::Mutex;
+use zeroize::Zeroizing;
+
+/// Login details kept in memory so an expired session can be renewed without prompting, the
+/// password is wiped when they are dropped
+#[derive(Clone)]
+pub struct Credentials {
+    pub username: String,
+    pub password: Zeroizing<String>,
+}
+
+/// State of the portal session shared by every tab
+#[derive(Default)]
+pub struct Session {
+    credentials: Mutex<Option<Credentials>>,
+    /// Term of the cart that was opened, `None` until one is or when the account has one cart
+    term: Mutex<Option<String>>,
+}
+
+impl Session {
+    /// Keeps the credentials of a successful login for logging back in, so unlike the copy typed
+    /// into the login form the password stays in memory until the session is dropped
+    pub fn remember(&self, username: &str, password: &str) {
+        *self.credentials.lock().unwrap() = Some(Credentials {
+            username: username.to_string(),
+            password: Zeroizing::new(password.to_string()),
+        });
+    }
+
+    /// Credentials of the last successful login, if there has been one
+    pub fn credentials(&self) -> Option<Credentials> {
+        self.credentials.lock().unwrap().clone()
+    }
+
+    /// Keeps the term of the opened cart so it can be opened again after logging back in
+    pub fn remember_term(&self, term: Option<&str>) {
+        *self.term.lock().unwrap() = term.map(str::to_string);
+    }
+
+    pub fn term(&self) -> Option<String> {
+        self.term.lock().unwrap().clone()
+    }
+}
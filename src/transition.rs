@@ -0,0 +1,82 @@
+use chromiumoxide::Page;
+use std::time::{Duration, Instant};
+
+use crate::error::{Phase, SniperError};
+use crate::wait::wait_for_any;
+
+/// Done to the matched element before the transition resolves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Click,
+}
+
+/// One way a page can go, recognized by `selector` showing up
+pub struct Outcome<'a, T> {
+    pub selector: &'a str,
+    pub outcome: T,
+    pub action: Option<Action>,
+}
+
+impl<'a, T> Outcome<'a, T> {
+    pub fn new(selector: &'a str, outcome: T) -> Self {
+        Self {
+            selector,
+            outcome,
+            action: None,
+        }
+    }
+
+    /// Clicks the element once it shows up
+    pub fn click(selector: &'a str, outcome: T) -> Self {
+        Self {
+            selector,
+            outcome,
+            action: Some(Action::Click),
+        }
+    }
+}
+
+/// Every outcome a page can settle on during `phase`, earlier outcomes win when several show up
+/// together
+pub struct Transition<'a, T> {
+    pub phase: Phase,
+    pub outcomes: Vec<Outcome<'a, T>>,
+}
+
+/// The outcome a transition settled on
+#[derive(Debug)]
+pub struct Matched<T> {
+    pub outcome: T,
+    /// Time from starting the race to the selector showing up
+    pub elapsed: Duration,
+}
+
+impl<'a, T> Transition<'a, T> {
+    pub fn new(phase: Phase, outcomes: Vec<Outcome<'a, T>>) -> Self {
+        Self { phase, outcomes }
+    }
+
+    /// Races every outcome's selector and runs the winner's action
+    pub async fn race(self, page: &Page, wait_time: u64) -> Result<Matched<T>, SniperError> {
+        let start = Instant::now();
+        let selectors: Vec<&str> = self
+            .outcomes
+            .iter()
+            .map(|outcome| outcome.selector)
+            .collect();
+        let index = wait_for_any(page, &selectors, wait_time, self.phase).await?;
+        let elapsed = start.elapsed();
+        let matched = self
+            .outcomes
+            .into_iter()
+            .nth(index)
+            .expect("wait_for_any returns an index into the selectors");
+        if let Some(Action::Click) = matched.action {
+            page.find_element(matched.selector).await?.click().await?;
+        }
+        Ok(Matched {
+            outcome: matched.outcome,
+            elapsed,
+        })
+    }
+}
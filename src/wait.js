@@ -0,0 +1,24 @@
+// Resolves with the index of the first selector in the page, or -1 after `timeout` ms.
+// Checks once up front, then again after every DOM mutation instead of polling.
+(selectors, timeout) =>
+  new Promise((resolve) => {
+    const find = () => selectors.findIndex((selector) => document.querySelector(selector) !== null);
+    const found = find();
+    if (found >= 0) {
+      resolve(found);
+      return;
+    }
+    const observer = new MutationObserver(() => {
+      const found = find();
+      if (found >= 0) {
+        observer.disconnect();
+        clearTimeout(timer);
+        resolve(found);
+      }
+    });
+    const timer = setTimeout(() => {
+      observer.disconnect();
+      resolve(-1);
+    }, timeout);
+    observer.observe(document, { childList: true, subtree: true, attributes: true });
+  })
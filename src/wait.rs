@@ -0,0 +1,111 @@
+use async_std::task::sleep;
+use chromiumoxide::error::CdpError;
+use chromiumoxide::{Element, Page};
+use std::time::{Duration, Instant};
+
+use crate::error::{Phase, SniperError};
+
+/// MutationObserver based wait injected into the page, shared with `benches/wait.rs`
+pub const WAIT_SCRIPT: &str = include_str!("wait.js");
+/// Longest single wait in the page, kept well under the CDP request timeout
+const WAIT_SLICE: Duration = Duration::from_secs(5);
+/// Pause before re-injecting after a navigation tore down the page's scripts
+const NAVIGATION_RETRY: Duration = Duration::from_millis(20);
+/// What Chrome answers with when a navigation replaces the document mid evaluation
+const NAVIGATION_ERRORS: [&str; 4] = [
+    "Execution context was destroyed",
+    "Cannot find context with specified id",
+    "Inspected target navigated or closed",
+    "Promise was collected",
+];
+
+/// Whether `error` only means the page navigated away from the wait
+fn navigated(error: &CdpError) -> bool {
+    let message = match error {
+        CdpError::Chrome(error) => &error.message,
+        CdpError::ChromeMessage(message) => message,
+        _ => return false,
+    };
+    NAVIGATION_ERRORS
+        .iter()
+        .any(|navigation| message.contains(navigation))
+}
+
+/// Waits until one of `selectors` is in the page and returns the index of the first that is,
+/// earlier selectors win when several show up together.
+///
+/// The page itself watches for DOM changes, so this resolves as soon as an element is inserted
+/// without polling over CDP, and carries on through navigations.
+pub async fn wait_for_any(
+    page: &Page,
+    selectors: &[&str],
+    wait_time: u64,
+    phase: Phase,
+) -> Result<usize, SniperError> {
+    let deadline = Instant::now() + Duration::from_secs(wait_time);
+    let selectors_json = serde_json::to_string(selectors).unwrap_or_default();
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(SniperError::SelectorNotFound {
+                selector: selectors.join(", "),
+                phase,
+            });
+        }
+        let slice = remaining.min(WAIT_SLICE).as_millis();
+        match page
+            .evaluate_expression(format!("({WAIT_SCRIPT})({selectors_json}, {slice})"))
+            .await
+            .map(|result| result.into_value::<i64>())
+        {
+            Ok(Ok(found)) if found >= 0 => return Ok(found as usize),
+            Ok(_) => (),
+            // the document was replaced mid wait, watch the new one
+            Err(e) if navigated(&e) => sleep(NAVIGATION_RETRY).await,
+            // querySelector throws on a malformed selector, e.g. a typo in --selectors
+            Err(CdpError::JavascriptException(exception)) => {
+                let reason = exception
+                    .exception
+                    .and_then(|exception| exception.description)
+                    .unwrap_or(exception.text);
+                return Err(SniperError::Input(format!(
+                    "could not wait for {} during {phase}: {reason}",
+                    selectors.join(", ")
+                )));
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Waits for `selector` to be in the page and returns it
+pub async fn wait_element(
+    page: &Page,
+    selector: &str,
+    wait_time: u64,
+    phase: Phase,
+) -> Result<Element, SniperError> {
+    wait_for_any(page, &[selector], wait_time, phase).await?;
+    Ok(page.find_element(selector).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chrome(message: &str) -> CdpError {
+        CdpError::Chrome(chromiumoxide::types::Error {
+            code: -32000,
+            message: message.to_string(),
+        })
+    }
+
+    #[test]
+    fn retries_only_after_a_navigation() {
+        assert!(navigated(&chrome("Execution context was destroyed.")));
+        assert!(navigated(&chrome("Cannot find context with specified id")));
+        assert!(!navigated(&chrome("No target with given id found")));
+        assert!(!navigated(&CdpError::Timeout));
+        assert!(!navigated(&CdpError::NoResponse));
+    }
+}
@@ -0,0 +1,432 @@
+//! End to end runs against the built in practice portal.
+//!
+//! These drive a real headless Chromium, so they are ignored by default. Run them with
+//! `cargo test -- --ignored` on a machine with Chrome or Chromium installed.
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+const CART: &str = r#"
+duo = "none"
+latency = 0
+
+[[carts]]
+term = "Spring 2027"
+opens_in = 0
+
+[[carts.courses]]
+class_number = "4321"
+description = "CS 170-1 Introduction to Computer Science I"
+seats = 2
+capacity = 120
+
+[[carts.courses]]
+class_number = "5510"
+description = "MATH 221-3 Linear Algebra"
+seats = 0
+capacity = 35
+
+[[carts]]
+term = "Summer 2027"
+
+[[carts.courses]]
+class_number = "7001"
+description = "ENG 101-1 Expository Writing"
+seats = 3
+capacity = 20
+"#;
+
+/// Runs course-sniper against the practice portal serving `scenario`
+fn course_sniper(name: &str, scenario: &str, args: &[&str]) -> Output {
+    let path = std::env::temp_dir().join(format!("course-sniper-{name}.toml"));
+    std::fs::write(&path, scenario).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_course-sniper"))
+        .arg("--practice")
+        .arg("--practice-scenario")
+        .arg(&path)
+        .args(args)
+        .env("COURSE_SNIPER_USER", "student")
+        .env("COURSE_SNIPER_PASSWORD", "practice")
+        .env_remove("COURSE_SNIPER_TERM")
+        .env_remove("COURSE_SNIPER_CLASSES")
+        .env_remove("COURSE_SNIPER_AT")
+        .env_remove("COURSE_SNIPER_DUO_PASSCODE")
+        .env("COURSE_SNIPER_SESSION_KEY", "practice key")
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+#[ignore = "needs Chrome or Chromium"]
+fn lists_every_cart() {
+    let output = course_sniper("list-carts", CART, &["list-carts"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("Spring 2027"));
+    assert!(stdout(&output).contains("Summer 2027"));
+}
+
+#[test]
+#[ignore = "needs Chrome or Chromium"]
+fn lists_courses_with_class_numbers_and_seats() {
+    let output = course_sniper("list-courses", CART, &["list-courses", "--term", "Spring"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let courses = stdout(&output);
+    assert!(courses.contains("4321"));
+    assert!(courses.contains("Open 2/120"));
+    assert!(courses.contains("Closed"));
+}
+
+#[test]
+#[ignore = "needs Chrome or Chromium"]
+fn times_both_cart_readers_in_debug() {
+    let output = course_sniper(
+        "debug-courses",
+        CART,
+        &["--debug", "list-courses", "--term", "Spring"],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let notes = stdout(&output);
+    assert!(notes.contains("with one script and"), "{notes}");
+    assert!(!notes.contains("an incomplete read"), "{notes}");
+}
+
+#[test]
+#[ignore = "needs Chrome or Chromium"]
+fn prints_courses_as_json_and_csv() {
+    let output = course_sniper(
+        "courses-json",
+        CART,
+        &["list-courses", "--term", "Spring", "--output", "json"],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let courses: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(courses[0]["class_number"], "4321");
+    assert_eq!(courses[0]["availability"]["status"], "open");
+    assert_eq!(courses[0]["availability"]["available"], 2);
+    assert_eq!(courses[1]["availability"]["status"], "closed");
+
+    let output = course_sniper(
+        "courses-csv",
+        CART,
+        &["list-courses", "--term", "Spring", "--output", "csv"],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let csv = stdout(&output);
+    let mut lines = csv.lines();
+    assert!(lines
+        .next()
+        .unwrap()
+        .starts_with("class_number,description,credits,status"));
+    assert!(lines.next().unwrap().starts_with("4321,"));
+}
+
+#[test]
+#[ignore = "needs Chrome or Chromium"]
+fn rejects_a_wrong_password() {
+    let scenario = format!("password = \"something else\"\n{CART}");
+    let output = course_sniper("wrong-password", &scenario, &["list-carts"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stderr(&output).contains("invalid credentials"));
+}
+
+#[test]
+#[ignore = "needs Chrome or Chromium"]
+fn waits_through_duo() {
+    let scenario = CART.replace("duo = \"none\"", "duo = \"approve\"\nduo_delay = 1000");
+    let output = course_sniper("duo-approve", &scenario, &["list-carts"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("Spring 2027"));
+}
+
+#[test]
+#[ignore = "needs Chrome or Chromium"]
+fn fails_when_duo_times_out() {
+    let scenario = CART.replace("duo = \"none\"", "duo = \"timeout\"\nduo_delay = 1000");
+    let output = course_sniper("duo-timeout", &scenario, &["list-carts"]);
+    assert_eq!(output.status.code(), Some(4));
+    assert!(stderr(&output).contains("duo authentication timed out"));
+}
+
+#[test]
+#[ignore = "needs Chrome or Chromium"]
+fn resends_a_timed_out_duo_push() {
+    let scenario = CART.replace(
+        "duo = \"none\"",
+        "duo = \"approve\"\nduo_delay = 1000\nduo_timeouts = 1",
+    );
+    let output = course_sniper("duo-resend", &scenario, &["list-carts"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("Spring 2027"));
+}
+
+#[test]
+#[ignore = "needs Chrome or Chromium"]
+fn enters_a_duo_passcode() {
+    let scenario = CART.replace("duo = \"none\"", "duo = \"timeout\"\nduo_delay = 60000");
+    let output = course_sniper(
+        "duo-passcode",
+        &scenario,
+        &["--duo-passcode", "123456", "list-carts"],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("Spring 2027"));
+}
+
+#[test]
+#[ignore = "needs Chrome or Chromium"]
+fn fails_on_a_wrong_duo_passcode() {
+    let scenario = CART.replace("duo = \"none\"", "duo = \"timeout\"\nduo_delay = 60000");
+    let output = course_sniper(
+        "duo-wrong-passcode",
+        &scenario,
+        &["--duo-passcode", "000000", "list-carts"],
+    );
+    assert_eq!(output.status.code(), Some(13));
+    assert!(stderr(&output).contains("duo rejected the passcode"));
+}
+
+#[test]
+#[ignore = "needs Chrome or Chromium"]
+fn partially_enrolls_when_a_course_is_full() {
+    let output = course_sniper(
+        "enroll",
+        CART,
+        &[
+            "enroll", "--term", "Spring", "--class", "4321", "--class", "5510",
+        ],
+    );
+    assert_eq!(output.status.code(), Some(9), "{}", stderr(&output));
+    let results = stdout(&output);
+    assert!(results.contains("has been added to your schedule"));
+    assert!(results.contains("This class is full"));
+}
+
+#[test]
+#[ignore = "needs Chrome or Chromium"]
+fn enrolls_with_one_script() {
+    let output = course_sniper(
+        "fast-enroll",
+        CART,
+        &[
+            "enroll",
+            "--fast-enroll",
+            "--term",
+            "Spring",
+            "--class",
+            "4321",
+        ],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let results = stdout(&output);
+    assert!(results.contains("Confirm queued at"));
+    assert!(results.contains("has been added to your schedule"));
+}
+
+#[test]
+#[ignore = "needs Chrome or Chromium"]
+fn validating_before_registration_opens_fails() {
+    let scenario = CART.replace("opens_in = 0", "opens_in = 3600");
+    let output = course_sniper(
+        "validate-closed",
+        &scenario,
+        &["validate", "--term", "Spring", "--class", "4321"],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("not open yet"));
+}
+
+#[test]
+#[ignore = "needs Chrome or Chromium"]
+fn enrolls_once_registration_opens() {
+    let scenario = CART.replace("opens_in = 0", "opens_in = 20");
+    let output = course_sniper(
+        "enroll-at",
+        &scenario,
+        &[
+            "enroll", "--term", "Spring", "--class", "4321", "--at", "+25s",
+        ],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("has been added to your schedule"));
+}
+
+#[test]
+#[ignore = "needs Chrome or Chromium"]
+fn keeps_the_session_alive_until_registration() {
+    let scenario = format!("idle_timeout = 30\n{CART}");
+    let output = course_sniper(
+        "keepalive",
+        &scenario,
+        &[
+            "enroll",
+            "--term",
+            "Spring",
+            "--class",
+            "4321",
+            "--at",
+            "+50s",
+            "--keepalive-interval",
+            "10",
+        ],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("has been added to your schedule"));
+}
+
+#[test]
+#[ignore = "needs Chrome or Chromium"]
+fn logs_back_in_when_the_session_expires() {
+    let scenario = format!("idle_timeout = 20\n{CART}");
+    let output = course_sniper(
+        "relogin",
+        &scenario,
+        &[
+            "enroll",
+            "--term",
+            "Spring",
+            "--class",
+            "4321",
+            "--at",
+            "+35s",
+            "--no-keepalive",
+        ],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("logging back in"));
+    assert!(stdout(&output).contains("has been added to your schedule"));
+}
+
+#[test]
+#[ignore = "needs Chrome or Chromium"]
+fn logs_concurrent_snipers_back_in() {
+    let scenario = format!("idle_timeout = 20\n{CART}");
+    let output = course_sniper(
+        "relogin-snipers",
+        &scenario,
+        &[
+            "enroll",
+            "--term",
+            "Spring",
+            "--class",
+            "4321",
+            "--at",
+            "+35s",
+            "--no-keepalive",
+            "--snipers",
+            "2",
+        ],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("logged out during enroll, logging back in"));
+    assert!(stdout(&output).contains("has been added to your schedule"));
+}
+
+#[test]
+#[ignore = "needs Chrome or Chromium"]
+fn runs_on_a_saved_profile() {
+    let profile = std::env::temp_dir().join("course-sniper-profile");
+    let _ = std::fs::remove_dir_all(&profile);
+    let profile = profile.to_str().unwrap();
+
+    let output = course_sniper("profile", CART, &["--profile", profile, "check-session"]);
+    assert_eq!(output.status.code(), Some(12));
+
+    let output = course_sniper("profile", CART, &["--profile", profile, "list-carts"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("Spring 2027"));
+
+    // the login made by list-carts is still in the profile
+    let output = course_sniper("profile", CART, &["--profile", profile, "check-session"]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+
+    let output = course_sniper("profile", CART, &["check-session"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("--profile or --session-file"));
+}
+
+#[test]
+fn refuses_a_headless_session_save_without_a_user() {
+    // there is no window to log in by hand in, so it stops before starting the browser
+    let output = Command::new(env!("CARGO_BIN_EXE_course-sniper"))
+        .args(["--practice", "session", "save"])
+        .arg(std::env::temp_dir().join("course-sniper-headless.bin"))
+        .env_remove("COURSE_SNIPER_USER")
+        .env("COURSE_SNIPER_SESSION_KEY", "practice key")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2), "{}", stderr(&output));
+    assert!(stderr(&output).contains("--attach"));
+}
+
+#[test]
+#[ignore = "needs Chrome or Chromium"]
+fn saves_an_encrypted_session() {
+    let file = std::env::temp_dir().join("course-sniper-session.bin");
+    let _ = std::fs::remove_file(&file);
+    let path = file.to_str().unwrap();
+
+    let output = course_sniper(
+        "session",
+        CART,
+        &["session", "save", path, "--user", "student"],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let sealed = std::fs::read(&file).unwrap();
+    assert!(sealed.starts_with(b"course-sniper sealed v1"));
+    assert!(!String::from_utf8_lossy(&sealed).contains("saved_at"));
+
+    std::fs::write(&file, b"not a session").unwrap();
+    let output = course_sniper("session", CART, &["session", "load", path]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("could not decrypt"));
+}
+
+#[test]
+#[ignore = "needs Chrome or Chromium"]
+fn logs_in_with_a_password_from_the_vault() {
+    let vault = std::env::temp_dir().join("course-sniper-credentials.vault");
+    let _ = std::fs::remove_file(&vault);
+    let scenario = std::env::temp_dir().join("course-sniper-vault.toml");
+    std::fs::write(&scenario, CART).unwrap();
+    let sniper = || {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_course-sniper"));
+        command
+            .arg("--practice")
+            .arg("--practice-scenario")
+            .arg(&scenario)
+            .arg("--vault")
+            .arg(&vault)
+            .env("COURSE_SNIPER_USER", "student")
+            .env("COURSE_SNIPER_VAULT_KEY", "practice key")
+            .env_remove("COURSE_SNIPER_PASSWORD")
+            .env_remove("COURSE_SNIPER_TERM");
+        command
+    };
+
+    let mut add = sniper()
+        .args(["--password-stdin", "creds", "add"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    add.stdin.take().unwrap().write_all(b"practice\n").unwrap();
+    let output = add.wait_with_output().unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(!String::from_utf8_lossy(&std::fs::read(&vault).unwrap()).contains("practice"));
+
+    let output = sniper().args(["creds", "list"]).output().unwrap();
+    assert_eq!(stdout(&output).trim(), "student");
+
+    let output = sniper().arg("list-carts").output().unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("Spring 2027"));
+}